@@ -0,0 +1,392 @@
+use std::{
+    env,
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
+
+use clap::{parser::ValueSource, Arg, ArgAction, ArgGroup, Command, CommandFactory, Parser};
+use crossterm::style::Color;
+
+use cjv::{
+    glob::Glob,
+    load::Format,
+    path::{self, PathSegment},
+    Layout,
+};
+
+use crate::theme;
+
+/// A terminal viewer for JSON and other structured documents, browsed as
+/// Miller columns. Flags in `JMV_OPTS` apply first, and those given on the
+/// command line override them, as does `--no-FLAG` for a FLAG set there.
+#[derive(Parser)]
+#[command(name = "jmv", bin_name = "jmv", version, args_override_self = true, group(ArgGroup::new("format").args(["jsonl", "json5", "yaml", "toml"])))]
+pub struct Cli {
+    /// Input files, each opened in a tab of its own, or `-` or nothing to
+    /// read from stdin
+    paths: Vec<String>,
+    /// Reload automatically whenever the input file changes
+    #[arg(long)]
+    pub watch: bool,
+    /// Compare the two files given, browsing them merged with what was
+    /// added, removed or changed marked
+    #[arg(long, conflicts_with = "watch")]
+    pub diff: bool,
+    /// Parse JSON while reading it, for files too big to hold in memory twice
+    #[arg(long, conflicts_with = "format")]
+    pub stream: bool,
+    /// Note where each value of a JSON file starts, and show the line and
+    /// column of the selection's on the status line
+    #[arg(long, conflicts_with = "stream")]
+    pub positions: bool,
+    /// Read one JSON value per line
+    #[arg(long)]
+    jsonl: bool,
+    /// Read JSON5, which allows comments, trailing commas and unquoted keys
+    #[arg(long)]
+    json5: bool,
+    /// Read YAML
+    #[arg(long)]
+    yaml: bool,
+    /// Read TOML
+    #[arg(long)]
+    toml: bool,
+    /// Number of panels, counting the preview
+    #[arg(long, value_name = "N", default_value_t = 3, value_parser = clap::value_parser!(u8).range(2..))]
+    columns: u8,
+    /// Relative panel widths from left to right, or heights from top to
+    /// bottom when stacked, such as `1:2:3`
+    #[arg(long, value_name = "WEIGHTS", value_parser = parse_widths, conflicts_with = "columns")]
+    pub widths: Option<Widths>,
+    /// Lay the panels out side by side (`vertical`), or stacked top to
+    /// bottom (`horizontal`) for narrow terminals
+    #[arg(long, value_name = "LAYOUT", default_value = "vertical", value_parser = parse_layout)]
+    pub layout: Layout,
+    /// Show only the current level, as happens anyway in terminals narrower
+    /// than 48 columns unless the panels are stacked
+    #[arg(long)]
+    pub compact: bool,
+    /// List arrays longer than N as groups of entries, or 0 to never fold
+    #[arg(long, value_name = "N", default_value_t = 1000)]
+    pub fold: usize,
+    /// Entries in each group of a folded array
+    #[arg(long, value_name = "N", default_value_t = 100, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    pub group_size: usize,
+    /// Levels of nesting the preview shows before summarizing containers
+    #[arg(long, value_name = "N", default_value_t = 2)]
+    pub preview_depth: usize,
+    /// Preview strings as JSON, escapes and all, instead of as their text
+    #[arg(long)]
+    pub raw_strings: bool,
+    /// Indent nested JSON by N spaces, or by a `tab`, in the preview, the
+    /// viewer and whatever is written out
+    #[arg(long, value_name = "N", default_value = "2", value_parser = parse_indent)]
+    pub indent: Indent,
+    /// Preview arrays of scalars as a list of their values rather than as
+    /// JSON or by index
+    #[arg(long)]
+    pub preview_values: bool,
+    /// TOML or JSON file mapping roles like `selection_bg` to colors
+    #[arg(long, value_name = "FILE")]
+    pub theme: Option<PathBuf>,
+    /// Background of the selection, a name like `dark_blue` or `#rrggbb`,
+    /// over the theme's
+    #[arg(long, value_name = "COLOR", value_parser = parse_select_color)]
+    pub select_bg: Option<Color>,
+    /// Text color of the selection, over the theme's
+    #[arg(long, value_name = "COLOR", value_parser = parse_select_color)]
+    pub select_fg: Option<Color>,
+    /// Mark the selection with reverse video, which shows on any palette
+    #[arg(long)]
+    pub select_reverse: bool,
+    /// Mark true, false and null with ✓, ✗ and ∅ so they stand out
+    #[arg(long)]
+    pub glyphs: bool,
+    /// Show only the keys whose dotted path matches this glob, such as
+    /// `spec.*` or `**.name`, until Esc clears it
+    #[arg(long, value_name = "GLOB", value_parser = parse_only)]
+    pub only: Option<String>,
+    /// Start at the node at this jq path, such as `.items[3].meta`, in the
+    /// first file
+    #[arg(long, value_name = "PATH", value_parser = parse_goto)]
+    pub goto: Option<Goto>,
+    /// Print the node `--goto` picks out of the first file, or all of it, as
+    /// JSON instead of opening it, on one line with `--compact`
+    #[arg(long, conflicts_with_all = ["watch", "diff"])]
+    pub output: bool,
+    /// Print the path of the selection on quitting, as in
+    /// `path=$(jmv data.json --print-path-on-exit)`
+    #[arg(long)]
+    pub print_path_on_exit: bool,
+    /// Start at the root instead of where the file was left last time
+    #[arg(long)]
+    pub no_restore: bool,
+    /// Print how long reading and parsing took, and the node count, to stderr
+    #[arg(long)]
+    pub verbose: bool,
+    /// Forget where every file was left, then exit
+    #[arg(long, exclusive = true)]
+    pub clear_state: bool,
+}
+
+/// Panel width weights, one per panel.
+#[derive(Clone)]
+pub struct Widths(pub Vec<u16>);
+
+/// One level of `--indent`.
+#[derive(Clone)]
+pub struct Indent(pub String);
+
+/// The path given to `--goto`.
+#[derive(Clone)]
+pub struct Goto(pub Vec<PathSegment>);
+
+/// Splits `text` at whitespace, keeping anything in single or double
+/// quotes together, as a shell would for simple cases.
+fn split_words(text: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut quote = None;
+    for c in text.chars() {
+        match (quote, c) {
+            (Some(open), c) if c == open => quote = None,
+            (Some(_), c) => word.get_or_insert_with(String::new).push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                word.get_or_insert_with(String::new);
+            }
+            (None, c) if c.is_whitespace() => words.extend(word.take()),
+            (None, c) => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    words
+}
+
+/// `args` with the flags in `defaults` spliced in ahead of them, less those
+/// a flag in `args` overrides: the same flag, another of its group, or one
+/// either of them conflicts with. `--no-FLAG` in `args` drops a boolean
+/// FLAG from `defaults`, and goes itself.
+fn with_defaults(args: Vec<OsString>, defaults: &str) -> Vec<OsString> {
+    // `--clear-state` takes no other flags, so it goes without them.
+    if args.iter().any(|arg| arg == "--clear-state") {
+        return args;
+    }
+    let command = Cli::command();
+    let mut turned_off = Vec::new();
+    let mut rest = false;
+    let args: Vec<OsString> = args
+        .into_iter()
+        .filter(|arg| {
+            // Anything after `--` is a path.
+            rest |= arg == "--";
+            let flag = arg
+                .to_str()
+                .filter(|_| !rest)
+                .and_then(|arg| arg.strip_prefix("--"))
+                // Such as `--no-restore`, a flag of its own.
+                .filter(|name| find_long(&command, name).is_none())
+                .and_then(|name| name.strip_prefix("no-"))
+                .and_then(|name| find_long(&command, name))
+                .filter(|flag| matches!(flag.get_action(), ArgAction::SetTrue));
+            turned_off.extend(flag.map(Arg::get_id));
+            flag.is_none()
+        })
+        .collect();
+    let given: Vec<&Arg> = match command.clone().try_get_matches_from(&args) {
+        Ok(matches) => command
+            .get_arguments()
+            .filter(|arg| {
+                matches.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine)
+            })
+            .collect(),
+        // Parsing them with the defaults fails the same way.
+        Err(_) => return args,
+    };
+
+    let at = args.len().min(1);
+    let mut merged = args[..at].to_vec();
+    let mut words = split_words(defaults).into_iter();
+    while let Some(word) = words.next() {
+        let name = word
+            .strip_prefix("--")
+            .map(|name| name.split('=').next().unwrap_or(name));
+        let arg = name.and_then(|name| find_long(&command, name));
+        let value = arg
+            .filter(|arg| arg.get_action().takes_values() && !word.contains('='))
+            .and_then(|_| words.next());
+        let overridden = arg.is_some_and(|arg| {
+            turned_off.contains(&arg.get_id())
+                || given.iter().any(|given| overrides(&command, given, arg))
+        });
+        if !overridden {
+            merged.extend([word].into_iter().chain(value).map(OsString::from));
+        }
+    }
+    merged.extend(args.into_iter().skip(at));
+    merged
+}
+
+fn find_long<'c>(command: &'c Command, name: &str) -> Option<&'c Arg> {
+    command
+        .get_arguments()
+        .find(|arg| arg.get_long() == Some(name))
+}
+
+/// Whether giving `a` leaves no room for `b`.
+fn overrides(command: &Command, a: &Arg, b: &Arg) -> bool {
+    let conflicts = |a: &Arg, b: &Arg| {
+        command
+            .get_arg_conflicts_with(a)
+            .iter()
+            .any(|arg| arg.get_id() == b.get_id())
+    };
+    let grouped = command.get_groups().any(|group| {
+        let has = |arg: &Arg| group.get_args().any(|id| id == arg.get_id());
+        // `is_multiple` takes `&mut self`, though it only reads.
+        !group.clone().is_multiple() && has(a) && has(b)
+    });
+    a.get_id() == b.get_id() || grouped || conflicts(a, b) || conflicts(b, a)
+}
+
+fn parse_goto(text: &str) -> Result<Goto, String> {
+    path::parse_path(text)
+        .map(Goto)
+        .map_err(|e| format!("{e:#}"))
+}
+
+fn parse_only(text: &str) -> Result<String, String> {
+    Glob::new(text)
+        .map(|_| text.to_owned())
+        // The error quotes the glob with slashes for dots.
+        .map_err(|e| e.kind().to_string())
+}
+
+fn parse_select_color(text: &str) -> Result<Color, String> {
+    theme::parse_color(text).ok_or_else(|| "expected a color name or `#rrggbb`".to_owned())
+}
+
+fn parse_indent(text: &str) -> Result<Indent, String> {
+    match text.parse() {
+        _ if text == "tab" => Ok(Indent("\t".to_owned())),
+        Ok(spaces @ 0..=8) => Ok(Indent(" ".repeat(spaces))),
+        _ => Err("expected 0 to 8 spaces or `tab`".to_owned()),
+    }
+}
+
+fn parse_layout(text: &str) -> Result<Layout, String> {
+    match text {
+        "vertical" => Ok(Layout::Vertical),
+        "horizontal" => Ok(Layout::Horizontal),
+        _ => Err("expected `vertical` or `horizontal`".to_owned()),
+    }
+}
+
+fn parse_widths(text: &str) -> Result<Widths, String> {
+    let weights = text
+        .split(':')
+        .map(|weight| match weight.trim().parse() {
+            Ok(0) | Err(_) => Err(format!("`{weight}` is not a positive weight")),
+            Ok(weight) => Ok(weight),
+        })
+        .collect::<Result<Vec<u16>, _>>()?;
+    if weights.len() < 2 {
+        return Err("expected at least two weights, such as `1:2`".to_owned());
+    }
+    Ok(Widths(weights))
+}
+
+impl Cli {
+    /// Parses the command line with the flags in `JMV_OPTS` spliced in
+    /// ahead of it, exiting with usage on an error.
+    pub fn parse_with_env() -> Cli {
+        let args = env::args_os().collect();
+        let defaults = env::var("JMV_OPTS").unwrap_or_default();
+        Cli::parse_from(with_defaults(args, &defaults))
+    }
+
+    /// Input files in the order given, `None` standing for stdin.
+    pub fn paths(&self) -> Vec<Option<&str>> {
+        if self.paths.is_empty() {
+            return vec![None];
+        }
+        self.paths
+            .iter()
+            .map(|path| Some(path.as_str()).filter(|path| *path != "-"))
+            .collect()
+    }
+
+    pub fn columns(&self) -> usize {
+        usize::from(self.columns)
+    }
+
+    /// Input format given by a flag, otherwise guessed from the extension
+    /// of `path`.
+    pub fn format(&self, path: Option<&str>) -> Format {
+        if self.jsonl {
+            Format::Jsonl
+        } else if self.json5 {
+            Format::Json5
+        } else if self.yaml {
+            Format::Yaml
+        } else if self.toml {
+            Format::Toml
+        } else {
+            path.map_or(Format::Json, |path| Format::from_path(Path::new(path)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(defaults: &str, args: &[&str]) -> Cli {
+        let args = ["jmv"].iter().chain(args).map(OsString::from).collect();
+        Cli::try_parse_from(with_defaults(args, defaults)).unwrap()
+    }
+
+    #[test]
+    fn a_format_flag_overrides_another_from_the_environment() {
+        let cli = parse("--yaml", &["--toml", "f.toml"]);
+        assert!(cli.format(Some("f.toml")) == Format::Toml);
+        assert!(!cli.yaml);
+    }
+
+    #[test]
+    fn columns_override_widths_from_the_environment() {
+        let cli = parse("--widths 1:2", &["--columns", "4", "f.json"]);
+        assert_eq!(cli.columns(), 4);
+        assert!(cli.widths.is_none());
+        let cli = parse("--widths=1:2 --compact", &["f.json"]);
+        assert_eq!(cli.widths.map(|widths| widths.0), Some(vec![1, 2]));
+        assert!(cli.compact);
+    }
+
+    #[test]
+    fn a_format_flag_overrides_stream_from_the_environment() {
+        let cli = parse("--stream --watch", &["--yaml", "f.yaml"]);
+        assert!(!cli.stream);
+        assert!(cli.watch);
+        assert!(cli.format(None) == Format::Yaml);
+    }
+
+    #[test]
+    fn no_turns_off_a_flag_from_the_environment() {
+        let cli = parse(
+            "--compact --watch",
+            &["--no-compact", "--no-restore", "f.json"],
+        );
+        assert!(!cli.compact);
+        assert!(cli.watch);
+        assert!(cli.no_restore);
+        assert_eq!(cli.paths(), [Some("f.json")]);
+    }
+
+    #[test]
+    fn paths_after_a_double_dash_are_left_alone() {
+        let cli = parse("--compact", &["--", "--no-compact"]);
+        assert!(cli.compact);
+        assert_eq!(cli.paths(), [Some("--no-compact")]);
+    }
+}
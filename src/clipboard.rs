@@ -0,0 +1,32 @@
+/// The system clipboard, or a list of text to print on exit when there is
+/// none, such as over SSH or on a headless machine.
+pub struct Clipboard {
+    inner: Option<arboard::Clipboard>,
+    unsent: Vec<String>,
+}
+
+impl Clipboard {
+    pub fn new() -> Clipboard {
+        Clipboard {
+            inner: arboard::Clipboard::new().ok(),
+            unsent: Vec::new(),
+        }
+    }
+
+    /// Copies `text`, returning false if it was kept for printing on exit
+    /// instead.
+    pub fn copy(&mut self, text: String) -> bool {
+        if let Some(inner) = &mut self.inner {
+            if inner.set_text(text.as_str()).is_ok() {
+                return true;
+            }
+        }
+        self.unsent.push(text);
+        false
+    }
+
+    /// Text that couldn't be copied, oldest first.
+    pub fn unsent(&self) -> &[String] {
+        &self.unsent
+    }
+}
@@ -0,0 +1,152 @@
+//! Structural differences between two documents, for browsing them as one
+//! merged document with every node marked by how it changed.
+
+use std::collections::HashMap;
+
+use serde_json::{Map, Value};
+
+/// How a node of the merged document differs between the two sides.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Change {
+    Same,
+    /// Only in the new document.
+    Added,
+    /// Only in the old document.
+    Removed,
+    /// In both, but not equal.
+    Changed,
+}
+
+/// The changes between two documents, node by node. Object entries go by
+/// their key and array entries by their index, and unchanged branches are
+/// left out.
+pub struct DiffTree {
+    change: Change,
+    /// The old value of a changed node that has no children to tell apart.
+    old: Option<Value>,
+    children: HashMap<String, DiffTree>,
+}
+
+/// Compares `old` with `new` key by key, and arrays index by index.
+pub fn diff(old: &Value, new: &Value) -> DiffTree {
+    if old == new {
+        return DiffTree::leaf(Change::Same);
+    }
+    let mut children = HashMap::new();
+    match (old, new) {
+        (Value::Object(old), Value::Object(new)) => {
+            for (key, value) in old {
+                let child = match new.get(key) {
+                    Some(other) => diff(value, other),
+                    None => DiffTree::leaf(Change::Removed),
+                };
+                children.insert(key.clone(), child);
+            }
+            for key in new.keys().filter(|key| !old.contains_key(*key)) {
+                children.insert(key.clone(), DiffTree::leaf(Change::Added));
+            }
+        }
+        (Value::Array(old), Value::Array(new)) => {
+            for index in 0..old.len().max(new.len()) {
+                let child = match (old.get(index), new.get(index)) {
+                    (Some(value), Some(other)) => diff(value, other),
+                    (Some(_), None) => DiffTree::leaf(Change::Removed),
+                    _ => DiffTree::leaf(Change::Added),
+                };
+                children.insert(index.to_string(), child);
+            }
+        }
+        _ => {
+            return DiffTree {
+                change: Change::Changed,
+                old: Some(old.clone()),
+                children,
+            }
+        }
+    }
+    children.retain(|_, child| child.change != Change::Same);
+    DiffTree {
+        change: Change::Changed,
+        old: None,
+        children,
+    }
+}
+
+/// The union of `old` and `new`: every key and index of either, with the
+/// value from `new` wherever it has one.
+pub fn merge(old: &Value, new: &Value) -> Value {
+    match (old, new) {
+        (Value::Object(old), Value::Object(new)) => {
+            // Keys keep their place in `old`, and added ones come after.
+            let mut merged = Map::new();
+            for (key, value) in old {
+                let value = match new.get(key) {
+                    Some(other) => merge(value, other),
+                    None => value.clone(),
+                };
+                merged.insert(key.clone(), value);
+            }
+            for (key, value) in new {
+                if !old.contains_key(key) {
+                    merged.insert(key.clone(), value.clone());
+                }
+            }
+            Value::Object(merged)
+        }
+        (Value::Array(old), Value::Array(new)) => {
+            let merged = (0..old.len().max(new.len()))
+                .map(|index| match (old.get(index), new.get(index)) {
+                    (Some(value), Some(other)) => merge(value, other),
+                    (Some(value), None) | (None, Some(value)) => value.clone(),
+                    (None, None) => Value::Null,
+                })
+                .collect();
+            Value::Array(merged)
+        }
+        _ => new.clone(),
+    }
+}
+
+impl DiffTree {
+    fn leaf(change: Change) -> DiffTree {
+        DiffTree {
+            change,
+            old: None,
+            children: HashMap::new(),
+        }
+    }
+
+    /// The change to the node at `path` of the merged document. Below an
+    /// added or removed node, everything is added or removed with it.
+    pub fn change_at(&self, path: &[String]) -> Change {
+        match path.split_first() {
+            None => self.change,
+            Some((key, rest)) => match (self.change, self.children.get(key)) {
+                (Change::Added | Change::Removed, _) => self.change,
+                (_, Some(child)) => child.change_at(rest),
+                (_, None) => Change::Same,
+            },
+        }
+    }
+
+    /// What the node at `path` was before, if it is a changed scalar or
+    /// changed type.
+    pub fn old_at(&self, path: &[String]) -> Option<&Value> {
+        match path.split_first() {
+            None => self.old.as_ref(),
+            Some((key, rest)) => self.children.get(key)?.old_at(rest),
+        }
+    }
+
+    /// How many places the documents differ in: each added or removed
+    /// branch once, and each changed value that isn't made of others.
+    pub fn differences(&self) -> usize {
+        match self.change {
+            Change::Same => 0,
+            Change::Changed if !self.children.is_empty() => {
+                self.children.values().map(DiffTree::differences).sum()
+            }
+            _ => 1,
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use serde_json::Value;
+
+use crate::{
+    load,
+    path::{self, format_path, PathSegment},
+    state::follow,
+    Pattern,
+};
+
+/// One line of the flat view.
+pub struct FlatRow<'a> {
+    pub path: Vec<PathSegment>,
+    /// `path` as a jq path.
+    pub text: String,
+    pub value: &'a Value,
+}
+
+/// A single-pane list of every leaf in the document, a scalar or an empty
+/// container, by its full path.
+pub struct FlatState<'a> {
+    rows: Vec<FlatRow<'a>>,
+    index: usize,
+    scroll: usize,
+    height: usize,
+}
+
+impl<'a> FlatState<'a> {
+    /// Lists the leaves of `root` with the first at or below `path`
+    /// selected, showing `height` rows at a time.
+    pub fn new(root: &'a Value, path: &[PathSegment], height: usize) -> FlatState<'a> {
+        let mut rows = Vec::new();
+        path::walk(root, &mut Vec::new(), &mut |path, value| {
+            let leaf = match value {
+                Value::Object(map) => map.is_empty(),
+                Value::Array(arr) => arr.is_empty(),
+                _ => true,
+            };
+            if leaf {
+                rows.push(FlatRow {
+                    path: path.to_vec(),
+                    text: format_path(path),
+                    value,
+                });
+            }
+        });
+        let mut flat = FlatState {
+            rows,
+            index: 0,
+            scroll: 0,
+            height,
+        };
+        let found = flat.rows.iter().position(|row| row.path.starts_with(path));
+        flat.select(found.unwrap_or(0));
+        flat
+    }
+
+    pub fn rows(&self) -> &[FlatRow<'a>] {
+        &self.rows
+    }
+
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn scroll(&self) -> usize {
+        self.scroll
+    }
+
+    /// Path of the selected row, or the root when there are no leaves.
+    pub fn selected_path(&self) -> &[PathSegment] {
+        self.rows.get(self.index).map_or(&[], |row| &row.path)
+    }
+
+    pub fn resize(&mut self, height: usize) {
+        self.height = height;
+        self.select(self.index);
+    }
+
+    /// Selects the row shown `row` lines below the top of the view.
+    pub fn select_row(&mut self, row: usize) {
+        if row < self.height && self.scroll + row < self.rows.len() {
+            self.select(self.scroll + row);
+        }
+    }
+
+    pub fn inc_index(&mut self, count: usize) {
+        self.select(self.index.saturating_add(count));
+    }
+
+    pub fn dec_index(&mut self, count: usize) {
+        self.select(self.index.saturating_sub(count));
+    }
+
+    pub fn goto_first(&mut self) {
+        self.select(0);
+    }
+
+    pub fn goto_last(&mut self) {
+        self.select(self.rows.len().saturating_sub(1));
+    }
+
+    pub fn goto(&mut self, index: usize) {
+        self.select(index);
+    }
+
+    /// Finds the first row `skip` or more rows on from the selection,
+    /// wrapping around, whose path or scalar value `pattern` matches.
+    pub fn find(&self, pattern: &Pattern, skip: usize, forward: bool) -> Option<usize> {
+        let size = self.rows.len();
+        (skip..size + skip)
+            .map(|step| {
+                if forward {
+                    (self.index + step) % size
+                } else {
+                    (self.index + size - step % size) % size
+                }
+            })
+            .find(|index| {
+                let row = &self.rows[*index];
+                let scalar = match row.value {
+                    Value::Object(_) | Value::Array(_) => String::new(),
+                    Value::String(s) => s.clone(),
+                    scalar => load::scalar_text(scalar),
+                };
+                pattern.find(&row.text).is_some() || pattern.find(&scalar).is_some()
+            })
+    }
+
+    fn select(&mut self, index: usize) {
+        self.index = index.min(self.rows.len().saturating_sub(1));
+        self.scroll = follow(self.scroll, self.index, self.rows.len(), self.height);
+    }
+}
@@ -0,0 +1,157 @@
+//! Globs over the dotted key paths of a document, such as `spec.*` or
+//! `**.name`, for narrowing the view to the branches they pick out.
+
+use globset::{GlobBuilder, GlobMatcher};
+
+/// A compiled glob, in `globset` syntax with keys split on dots where paths
+/// split on slashes: `*` stands for any run of characters within a key, and
+/// a `**` key for any number of whole keys. A dot within a key is written
+/// `\.`, as in `app\.kubernetes\.io/name`. Array entries go by their index.
+pub struct Glob {
+    text: String,
+    /// Matches whole paths.
+    full: GlobMatcher,
+    /// Matches the first keys of paths, for each count of keys up to the
+    /// first `**`, or up to all but the last key if there is none.
+    prefixes: Vec<GlobMatcher>,
+    /// Where the first `**` is among the keys.
+    any: Option<usize>,
+    keys: usize,
+}
+
+/// Stands in for a slash within a key, which globset would take for the
+/// end of the key.
+const SLASH: &str = "\0";
+
+impl Glob {
+    pub fn new(pattern: &str) -> Result<Glob, globset::Error> {
+        let keys = split_keys(pattern);
+        let any = keys.iter().position(|key| key == "**");
+        let full = compile(&keys)?;
+        let prefixes = (1..=any.unwrap_or(keys.len() - 1))
+            .map(|len| compile(&keys[..len]))
+            .collect::<Result<_, _>>()?;
+        Ok(Glob {
+            text: pattern.to_owned(),
+            full,
+            prefixes,
+            any,
+            keys: keys.len(),
+        })
+    }
+
+    /// The glob as it was written.
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// Whether the entry at `path` is to be shown: it matches, it lies
+    /// under something that matches, or, if it is a `container`, something
+    /// below it still could.
+    pub fn shows(&self, path: &[String], container: bool) -> bool {
+        if container && self.could_match(path) {
+            return true;
+        }
+        let mut joined = String::new();
+        path.iter().enumerate().any(|(i, key)| {
+            if i > 0 {
+                joined.push('/');
+            }
+            joined.push_str(&key.replace('/', SLASH));
+            self.full.is_match(&joined)
+        })
+    }
+
+    /// Whether some path going on from `path` could match the glob, `path`
+    /// itself excluded.
+    fn could_match(&self, path: &[String]) -> bool {
+        // Past a `**` anything could still match, so only the keys before
+        // it need to.
+        let len = match self.any {
+            Some(any) if path.len() >= any => any,
+            _ if path.len() < self.keys => path.len(),
+            _ => return false,
+        };
+        len == 0 || self.prefixes[len - 1].is_match(join(&path[..len]))
+    }
+}
+
+/// Splits a glob into its keys on the dots that aren't escaped, with
+/// slashes swapped for `SLASH`.
+fn split_keys(pattern: &str) -> Vec<String> {
+    let mut keys = vec![String::new()];
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        // Never empty, as it starts with a key.
+        let key = keys.last_mut().unwrap();
+        match c {
+            '\\' => {
+                key.push(c);
+                key.extend(chars.next());
+            }
+            '.' => keys.push(String::new()),
+            '/' => key.push_str(SLASH),
+            c => key.push(c),
+        }
+    }
+    keys
+}
+
+fn compile(keys: &[String]) -> Result<GlobMatcher, globset::Error> {
+    let glob = GlobBuilder::new(&keys.join("/"))
+        .literal_separator(true)
+        .build()?;
+    Ok(glob.compile_matcher())
+}
+
+/// `path` the way `compile` joins the keys of a glob.
+fn join(path: &[String]) -> String {
+    path.iter()
+        .map(|key| key.replace('/', SLASH))
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(keys: &[&str]) -> Vec<String> {
+        keys.iter().map(|&key| key.to_owned()).collect()
+    }
+
+    #[test]
+    fn a_star_stands_for_one_key() {
+        let glob = Glob::new("spec.*").unwrap();
+        assert!(glob.shows(&path(&["spec"]), true));
+        assert!(glob.shows(&path(&["spec", "replicas"]), false));
+        assert!(glob.shows(&path(&["spec", "containers", "0"]), true));
+        assert!(!glob.shows(&path(&["status"]), true));
+        assert!(!glob.shows(&path(&["spec.replicas"]), false));
+    }
+
+    #[test]
+    fn a_double_star_stands_for_any_keys() {
+        let glob = Glob::new("**.name").unwrap();
+        assert!(glob.shows(&path(&["name"]), false));
+        assert!(glob.shows(&path(&["spec", "containers", "0", "name"]), false));
+        assert!(glob.shows(&path(&["spec", "containers"]), true));
+        assert!(!glob.shows(&path(&["spec", "image"]), false));
+        assert!(!glob.shows(&path(&["names"]), false));
+    }
+
+    #[test]
+    fn escaped_dots_stay_within_a_key() {
+        let glob = Glob::new(r"metadata.labels.app\.kubernetes\.io/*").unwrap();
+        let key = "app.kubernetes.io/name";
+        assert!(glob.shows(&path(&["metadata", "labels", key]), false));
+        assert!(glob.shows(&path(&["metadata", "labels"]), true));
+        assert!(!glob.shows(&path(&["metadata", "labels", "app"]), true));
+        assert!(!glob.shows(&path(&["metadata", "labels", "app.kubernetes"]), false));
+    }
+
+    #[test]
+    fn bad_globs_are_errors() {
+        assert!(Glob::new("spec.[").is_err());
+    }
+}
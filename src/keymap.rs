@@ -0,0 +1,467 @@
+use std::{collections::HashMap, env, fmt, fs, io, path::PathBuf};
+
+use anyhow::{bail, Context, Result};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde_json::Value;
+
+use cjv::load::{self, Format};
+
+/// Something a key can be bound to, in the panels, the tree view and the
+/// value viewer alike.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum Action {
+    Down,
+    Up,
+    Descend,
+    Ascend,
+    HalfPageDown,
+    HalfPageUp,
+    PageDown,
+    PageUp,
+    First,
+    Last,
+    Tree,
+    ToggleNode,
+    Table,
+    Flat,
+    View,
+    Wrap,
+    Sort,
+    Reverse,
+    Size,
+    Deeper,
+    Shallower,
+    Search,
+    NextMatch,
+    PrevMatch,
+    Jump,
+    Mark,
+    GotoMark,
+    Find,
+    Filter,
+    Cancel,
+    Command,
+    YankPath,
+    YankValue,
+    Reload,
+    NextTab,
+    PrevTab,
+    Help,
+    Quit,
+}
+
+impl Action {
+    /// Every action, in the order the help overlay lists them. Where one key
+    /// is bound to several, the first of them that applies is taken.
+    pub const ALL: [Action; 38] = [
+        Action::Down,
+        Action::Up,
+        Action::Descend,
+        Action::Ascend,
+        Action::HalfPageDown,
+        Action::HalfPageUp,
+        Action::PageDown,
+        Action::PageUp,
+        Action::First,
+        Action::Last,
+        Action::Tree,
+        Action::ToggleNode,
+        Action::Table,
+        Action::Flat,
+        Action::View,
+        Action::Wrap,
+        Action::Sort,
+        Action::Reverse,
+        Action::Size,
+        Action::Deeper,
+        Action::Shallower,
+        Action::Search,
+        Action::NextMatch,
+        Action::PrevMatch,
+        Action::Jump,
+        Action::Mark,
+        Action::GotoMark,
+        Action::Find,
+        Action::Filter,
+        Action::Cancel,
+        Action::Command,
+        Action::YankPath,
+        Action::YankValue,
+        Action::Reload,
+        Action::NextTab,
+        Action::PrevTab,
+        Action::Help,
+        Action::Quit,
+    ];
+
+    /// What the action is called in the config file.
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Down => "down",
+            Action::Up => "up",
+            Action::Descend => "descend",
+            Action::Ascend => "ascend",
+            Action::HalfPageDown => "half_page_down",
+            Action::HalfPageUp => "half_page_up",
+            Action::PageDown => "page_down",
+            Action::PageUp => "page_up",
+            Action::First => "first",
+            Action::Last => "last",
+            Action::Tree => "tree",
+            Action::ToggleNode => "toggle_node",
+            Action::Table => "table",
+            Action::Flat => "flat",
+            Action::View => "view",
+            Action::Wrap => "wrap",
+            Action::Sort => "sort",
+            Action::Reverse => "reverse",
+            Action::Size => "size",
+            Action::Deeper => "deeper",
+            Action::Shallower => "shallower",
+            Action::Search => "search",
+            Action::NextMatch => "next_match",
+            Action::PrevMatch => "prev_match",
+            Action::Jump => "jump",
+            Action::Mark => "mark",
+            Action::GotoMark => "goto_mark",
+            Action::Find => "find",
+            Action::Filter => "filter",
+            Action::Cancel => "cancel",
+            Action::Command => "command",
+            Action::YankPath => "yank",
+            Action::YankValue => "yank_value",
+            Action::Reload => "reload",
+            Action::NextTab => "next_tab",
+            Action::PrevTab => "prev_tab",
+            Action::Help => "help",
+            Action::Quit => "quit",
+        }
+    }
+
+    /// What the action does, for the help overlay.
+    pub fn description(self) -> &'static str {
+        match self {
+            Action::Down => "move down",
+            Action::Up => "move up",
+            Action::Descend => "go to child",
+            Action::Ascend => "go to parent",
+            Action::HalfPageDown => "half page down",
+            Action::HalfPageUp => "half page up",
+            Action::PageDown => "full page down",
+            Action::PageUp => "full page up",
+            Action::First => "first entry",
+            Action::Last => "last entry",
+            Action::Tree => "toggle the tree view",
+            Action::ToggleNode => "expand / collapse in the tree view",
+            Action::Table => "show arrays of objects as a table",
+            Action::Flat => "list every leaf by its path",
+            Action::View => "view the whole value",
+            Action::Wrap => "toggle wrap-around",
+            Action::Sort => "sort keys a-z, z-a, or as written",
+            Action::Reverse => "list arrays last entry first",
+            Action::Size => "show the size of the selection as JSON",
+            Action::Deeper => "preview more levels",
+            Action::Shallower => "preview fewer levels",
+            Action::Search => "search this level",
+            Action::NextMatch => "next match",
+            Action::PrevMatch => "previous match",
+            Action::Jump => "jump to the next key starting with a letter",
+            Action::Mark => "mark the selection with a letter",
+            Action::GotoMark => "go back to a marked selection",
+            Action::Find => "fuzzy find in the document",
+            Action::Filter => "filter this level by key",
+            Action::Cancel => "clear the filters, or close the viewer or flat view",
+            Action::Command => "jump to a jq path, :write[!] <file>, or :stats",
+            Action::YankPath => "copy the path",
+            Action::YankValue => "copy the value as JSON",
+            Action::Reload => "reload the file",
+            Action::NextTab => "next tab",
+            Action::PrevTab => "previous tab",
+            Action::Help => "show this help",
+            Action::Quit => "quit",
+        }
+    }
+
+    fn default_keys(self) -> &'static [&'static str] {
+        match self {
+            Action::Down => &["j", "Down"],
+            Action::Up => &["k", "Up"],
+            Action::Descend => &["l", "Right"],
+            Action::Ascend => &["h", "Left"],
+            Action::HalfPageDown => &["Ctrl-d"],
+            Action::HalfPageUp => &["Ctrl-u"],
+            Action::PageDown => &["Ctrl-f"],
+            Action::PageUp => &["Ctrl-b"],
+            Action::First => &["g g", "Home"],
+            Action::Last => &["G", "End"],
+            Action::Tree => &["t"],
+            Action::ToggleNode => &["Enter"],
+            Action::Table => &["T"],
+            Action::Flat => &["F"],
+            Action::View => &["v", "Enter"],
+            Action::Wrap => &["w"],
+            Action::Sort => &["s"],
+            Action::Reverse => &["R"],
+            Action::Size => &["b"],
+            Action::Deeper => &["+"],
+            Action::Shallower => &["-"],
+            Action::Search => &["/"],
+            Action::NextMatch => &["n"],
+            Action::PrevMatch => &["N"],
+            Action::Jump => &[";"],
+            Action::Mark => &["m"],
+            // Either quote, as in vim.
+            Action::GotoMark => &["'", "`"],
+            // Terminals send Ctrl-/ as the same byte as Ctrl-7.
+            Action::Find => &["Ctrl-/", "Ctrl-7"],
+            Action::Filter => &["f"],
+            Action::Cancel => &["Esc"],
+            Action::Command => &[":"],
+            Action::YankPath => &["y"],
+            Action::YankValue => &["Y"],
+            Action::Reload => &["r"],
+            Action::NextTab => &["Tab", "g t"],
+            Action::PrevTab => &["Shift-Tab", "g T"],
+            Action::Help => &["?"],
+            Action::Quit => &["q"],
+        }
+    }
+}
+
+/// A key with its modifiers, Shift left out of printable characters since
+/// it is already in their case.
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Key {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl From<KeyEvent> for Key {
+    fn from(event: KeyEvent) -> Key {
+        let mut modifiers = event.modifiers;
+        if let KeyCode::Char(_) | KeyCode::BackTab = event.code {
+            modifiers.remove(KeyModifiers::SHIFT);
+        }
+        Key {
+            code: event.code,
+            modifiers,
+        }
+    }
+}
+
+impl Key {
+    /// Parses a key such as `j`, `G`, `Down`, `Space`, `Ctrl-d` or `Alt-x`.
+    fn parse(spec: &str) -> Option<Key> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = spec;
+        // A lone `-` is the key itself rather than a modifier separator.
+        while let Some((modifier, key)) = rest.split_once('-').filter(|(_, key)| !key.is_empty()) {
+            modifiers |= match modifier.to_lowercase().as_str() {
+                "ctrl" | "c" => KeyModifiers::CONTROL,
+                "alt" | "m" => KeyModifiers::ALT,
+                "shift" | "s" => KeyModifiers::SHIFT,
+                _ => return None,
+            };
+            rest = key;
+        }
+        let mut chars = rest.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) if modifiers.contains(KeyModifiers::SHIFT) => {
+                KeyCode::Char(c.to_ascii_uppercase())
+            }
+            (Some(c), None) => KeyCode::Char(c),
+            // Terminals send Shift-Tab as a key of its own.
+            _ if modifiers.contains(KeyModifiers::SHIFT) && rest.eq_ignore_ascii_case("tab") => {
+                KeyCode::BackTab
+            }
+            _ => match rest.to_lowercase().as_str() {
+                "space" => KeyCode::Char(' '),
+                "enter" | "return" => KeyCode::Enter,
+                "esc" | "escape" => KeyCode::Esc,
+                "tab" => KeyCode::Tab,
+                "backspace" => KeyCode::Backspace,
+                "delete" | "del" => KeyCode::Delete,
+                "insert" => KeyCode::Insert,
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                name => KeyCode::F(name.strip_prefix('f')?.parse().ok()?),
+            },
+        };
+        Some(Key::from(KeyEvent::new(code, modifiers)))
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            f.write_str("Ctrl-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            f.write_str("Alt-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            f.write_str("Shift-")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => f.write_str("Space"),
+            KeyCode::Char(c) => write!(f, "{c}"),
+            KeyCode::Up => f.write_str("↑"),
+            KeyCode::Down => f.write_str("↓"),
+            KeyCode::Left => f.write_str("←"),
+            KeyCode::Right => f.write_str("→"),
+            KeyCode::F(n) => write!(f, "F{n}"),
+            KeyCode::PageUp => f.write_str("PageUp"),
+            KeyCode::PageDown => f.write_str("PageDown"),
+            KeyCode::BackTab => f.write_str("Shift-Tab"),
+            code => write!(f, "{code:?}"),
+        }
+    }
+}
+
+/// Which actions each key, or short sequence of keys, is bound to, in the
+/// order they were bound.
+pub struct KeyMap {
+    bindings: Vec<(Vec<Key>, Action)>,
+}
+
+impl Default for KeyMap {
+    fn default() -> KeyMap {
+        KeyMap::build(&HashMap::new())
+    }
+}
+
+impl KeyMap {
+    /// Reads the `[keys]` table of the config file, which binds action names
+    /// to a key or a list of them, such as `down = ["j", "Ctrl-n"]`. A
+    /// sequence is written with spaces, as in `"g g"`. Actions left out keep
+    /// their default keys, except any taken by the config. There being no
+    /// config file is no error.
+    pub fn load() -> Result<KeyMap> {
+        let Some(path) = config_file() else {
+            return Ok(KeyMap::default());
+        };
+        let text = match fs::read_to_string(&path) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(KeyMap::default()),
+            text => text.context("Config Input")?,
+        };
+        let config = load::parse(&text, Format::Toml)
+            .context("Config Input")?
+            .value;
+        let Some(keys) = config.get("keys") else {
+            return Ok(KeyMap::default());
+        };
+        let Value::Object(keys) = keys else {
+            bail!("Config Input: expected `keys` to be a table");
+        };
+
+        let mut bound = HashMap::new();
+        for (name, specs) in keys {
+            let Some(action) = Action::ALL.into_iter().find(|action| action.name() == name) else {
+                bail!("Config Input: unknown action `{name}`");
+            };
+            let specs = match specs {
+                Value::String(spec) => Some(vec![spec.as_str()]),
+                Value::Array(specs) => specs.iter().map(Value::as_str).collect(),
+                _ => None,
+            };
+            let Some(specs) = specs else {
+                bail!("Config Input: expected a key or list of keys for `{name}`");
+            };
+            let sequences = specs
+                .into_iter()
+                .map(|spec| {
+                    parse_sequence(spec)
+                        .with_context(|| format!("Config Input: invalid key `{spec}` for `{name}`"))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            bound.insert(action.name(), sequences);
+        }
+        Ok(KeyMap::build(&bound))
+    }
+
+    /// Binds each action in `bound` to its keys and every other action to
+    /// whichever of its defaults `bound` leaves free.
+    fn build(bound: &HashMap<&str, Vec<Vec<Key>>>) -> KeyMap {
+        let taken: Vec<&Vec<Key>> = bound.values().flatten().collect();
+        let mut bindings = Vec::new();
+        for action in Action::ALL {
+            let sequences = match bound.get(action.name()) {
+                Some(sequences) => sequences.clone(),
+                None => action
+                    .default_keys()
+                    .iter()
+                    .filter_map(|spec| parse_sequence(spec))
+                    .filter(|sequence| !taken.contains(&sequence))
+                    .collect(),
+            };
+            bindings.extend(sequences.into_iter().map(|sequence| (sequence, action)));
+        }
+        KeyMap { bindings }
+    }
+
+    /// Adds `key` to the keys typed so far in `pending`, returning what the
+    /// sequence is bound to once it is complete. `pending` is left holding
+    /// the keys of a sequence that isn't yet. A sequence that goes nowhere
+    /// starts over from its last key.
+    pub fn press(&self, pending: &mut Vec<Key>, key: Key) -> Vec<Action> {
+        pending.push(key);
+        loop {
+            let actions: Vec<Action> = self
+                .bindings
+                .iter()
+                .filter(|(bound, _)| bound == pending)
+                .map(|(_, action)| *action)
+                .collect();
+            if !actions.is_empty() {
+                pending.clear();
+                return actions;
+            }
+            let is_prefix = self
+                .bindings
+                .iter()
+                .any(|(bound, _)| bound.len() > pending.len() && bound.starts_with(pending));
+            if is_prefix {
+                return Vec::new();
+            }
+            if pending.len() == 1 {
+                pending.clear();
+                return Vec::new();
+            }
+            pending.drain(..pending.len() - 1);
+        }
+    }
+
+    /// The keys bound to `action`, as the help overlay shows them.
+    pub fn keys_label(&self, action: Action) -> String {
+        let sequences: Vec<String> = self
+            .bindings
+            .iter()
+            .filter(|(_, bound)| *bound == action)
+            .map(|(sequence, _)| sequence.iter().map(Key::to_string).collect())
+            .collect();
+        sequences.join(" ")
+    }
+}
+
+fn parse_sequence(spec: &str) -> Option<Vec<Key>> {
+    let keys = spec
+        .split_whitespace()
+        .map(Key::parse)
+        .collect::<Option<Vec<_>>>()?;
+    (!keys.is_empty()).then_some(keys)
+}
+
+/// `$XDG_CONFIG_HOME/jmv/config.toml`, or under `~/.config` without it.
+fn config_file() -> Option<PathBuf> {
+    let dir = match env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+    Some(dir.join("jmv").join("config.toml"))
+}
@@ -0,0 +1,19 @@
+//! The document model behind jmv: loading JSON-like input into a
+//! [`serde_json::Value`] and navigating it as Miller columns, as many and
+//! as wide as `--columns` and `--widths` ask for.
+
+pub mod diff;
+pub mod flat;
+pub mod glob;
+pub mod load;
+pub mod path;
+pub mod source;
+mod state;
+pub mod stats;
+pub mod table;
+pub mod tree;
+pub mod watch;
+
+pub use state::{
+    Bounds, Case, KeyOrder, Layout, PanelSide, PanelState, Pattern, ProgramState, Snapshot, ViewKey,
+};
@@ -0,0 +1,406 @@
+use std::{
+    fmt::Display,
+    io::{BufRead, BufReader, Read},
+    path::Path,
+};
+
+use anyhow::{anyhow, Context, Result};
+
+use serde::Serialize;
+use serde_json::{ser::PrettyFormatter, Map, Number, Serializer, Value};
+use unicode_width::UnicodeWidthStr;
+
+use crate::source::{self, SourceMap};
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    /// One JSON value per line, gathered into a top-level array.
+    Jsonl,
+    /// JSON5, which allows comments, trailing commas, unquoted keys and
+    /// single-quoted strings.
+    Json5,
+    Yaml,
+    Toml,
+}
+
+impl Format {
+    /// Guesses the format from a file extension, defaulting to JSON.
+    pub fn from_path(path: &Path) -> Format {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("jsonl" | "ndjson") => Format::Jsonl,
+            Some("json5") => Format::Json5,
+            Some("yaml" | "yml") => Format::Yaml,
+            Some("toml") => Format::Toml,
+            _ => Format::Json,
+        }
+    }
+}
+
+pub struct Parsed {
+    pub value: Value,
+    /// Describes input that was skipped rather than failing the whole parse.
+    pub warning: Option<String>,
+    /// Where each value starts in the text, when asked for.
+    pub source: Option<SourceMap>,
+}
+
+/// Marks the start of UTF-8 text written by some tools, and is no part of
+/// the document.
+const BOM: &str = "\u{feff}";
+
+/// Parses `text` as `format`. Plain JSON that fails to parse is retried as
+/// JSONL, and kept as such if every non-empty line was a value of its own.
+/// Otherwise anything after the first JSON value is ignored with a warning.
+pub fn parse(text: &str, format: Format) -> Result<Parsed> {
+    let text = text.strip_prefix(BOM).unwrap_or(text);
+    match format {
+        Format::Json => {
+            let mut values = serde_json::Deserializer::from_str(text).into_iter::<Value>();
+            // Blank input has no first value, so have it fail as from_str would.
+            let first = values.next().unwrap_or_else(|| serde_json::from_str(text));
+            let trailing = text[values.byte_offset()..].trim_start();
+            match first {
+                Ok(value) if trailing.is_empty() => Ok(Parsed {
+                    value,
+                    warning: None,
+                    source: None,
+                }),
+                first => match (parse_jsonl(text), first) {
+                    (Ok((records, errors)), _) if errors.is_empty() && records.len() > 1 => {
+                        Ok(Parsed {
+                            value: Value::Array(records),
+                            warning: None,
+                            source: None,
+                        })
+                    }
+                    (_, Ok(value)) => {
+                        let (line, column) = line_column(text, text.len() - trailing.len());
+                        Ok(Parsed {
+                            value,
+                            warning: Some(format!(
+                                "Ignored trailing data from line {line} column {column}"
+                            )),
+                            source: None,
+                        })
+                    }
+                    (_, Err(e)) => {
+                        Err(located(text, e.line(), e.column(), &e)).context("Json Deserialization")
+                    }
+                },
+            }
+        }
+        Format::Json5 => {
+            let value = json5::from_str(text)
+                .map_err(|e| match &e {
+                    json5::Error::Message {
+                        msg,
+                        location: Some(at),
+                    } => {
+                        // Parse errors quote the line already, so keep only
+                        // what was expected there.
+                        let message = msg.lines().last().unwrap_or(msg);
+                        let message = message.trim_start_matches([' ', '=']);
+                        located(text, at.line, at.column, &message)
+                    }
+                    _ => e.into(),
+                })
+                .context("Json5 Deserialization")?;
+            Ok(Parsed {
+                value,
+                warning: None,
+                source: None,
+            })
+        }
+        Format::Jsonl => {
+            let (records, errors) = parse_jsonl(text)?;
+            let warning = errors.first().map(|first| match errors.len() {
+                1 => format!("Skipped {first}"),
+                n => format!("Skipped {n} lines, first {first}"),
+            });
+            Ok(Parsed {
+                value: Value::Array(records),
+                warning,
+                source: None,
+            })
+        }
+        Format::Yaml => {
+            let mut yaml: serde_yaml::Value = serde_yaml::from_str(text)
+                .map_err(|e| match e.location() {
+                    Some(at) => located(text, at.line(), at.column(), &e),
+                    None => e.into(),
+                })
+                .context("Yaml Deserialization")?;
+            yaml.apply_merge().context("Yaml Deserialization")?;
+            Ok(Parsed {
+                value: yaml_to_json(yaml),
+                warning: None,
+                source: None,
+            })
+        }
+        Format::Toml => {
+            let table: toml::Table = toml::from_str(text)
+                .map_err(|e| match e.span() {
+                    Some(span) => {
+                        let (line, column) = line_column(text, span.start);
+                        located(text, line, column, &e.message())
+                    }
+                    None => e.into(),
+                })
+                .context("Toml Deserialization")?;
+            Ok(Parsed {
+                value: toml_to_json(toml::Value::Table(table)),
+                warning: None,
+                source: None,
+            })
+        }
+    }
+}
+
+/// Parses raw input as `format`. UTF-16 with a byte order mark is
+/// converted, and anything else that isn't valid UTF-8 has its bad bytes
+/// replaced, with a warning saying where the first of them was. With
+/// `locate`, notes where each value starts in the text as well.
+pub fn parse_bytes(bytes: Vec<u8>, format: Format, locate: bool) -> Result<Parsed> {
+    let (text, invalid) = decode(bytes);
+    let mut parsed = parse(&text, format)?;
+    if locate {
+        let text = text.strip_prefix(BOM).unwrap_or(&text);
+        parsed.source = source::locate(text, format);
+    }
+    parsed.warning = match (invalid, parsed.warning) {
+        (Some(invalid), Some(warning)) => Some(format!("{invalid}; {warning}")),
+        (invalid, warning) => invalid.or(warning),
+    };
+    Ok(parsed)
+}
+
+/// Decodes input as text, describing any bytes that had to be replaced.
+fn decode(bytes: Vec<u8>) -> (String, Option<String>) {
+    let little_endian = match bytes.get(..2) {
+        Some([0xff, 0xfe]) => Some(true),
+        Some([0xfe, 0xff]) => Some(false),
+        _ => None,
+    };
+    if let Some(little_endian) = little_endian {
+        let units: Vec<u16> = bytes[2..]
+            .chunks_exact(2)
+            .map(|pair| {
+                let pair = [pair[0], pair[1]];
+                if little_endian {
+                    u16::from_le_bytes(pair)
+                } else {
+                    u16::from_be_bytes(pair)
+                }
+            })
+            .collect();
+        let text = String::from_utf16_lossy(&units);
+        let invalid = text
+            .contains(char::REPLACEMENT_CHARACTER)
+            .then(|| "Replaced invalid UTF-16".to_owned());
+        return (text, invalid);
+    }
+    match String::from_utf8(bytes) {
+        Ok(text) => (text, None),
+        Err(e) => {
+            let offset = e.utf8_error().valid_up_to();
+            let text = String::from_utf8_lossy(e.as_bytes()).into_owned();
+            (
+                text,
+                Some(format!("Replaced invalid UTF-8 from byte {offset}")),
+            )
+        }
+    }
+}
+
+/// Parses JSON as it is read from `reader`, so the text is never held in
+/// memory alongside the parsed value. Unlike `parse` there is no retrying
+/// as JSONL, and errors give the location without quoting the line.
+pub fn parse_reader(reader: impl Read) -> Result<Parsed> {
+    let mut reader = BufReader::new(reader);
+    if reader
+        .fill_buf()
+        .context("Json Deserialization")?
+        .starts_with(BOM.as_bytes())
+    {
+        reader.consume(BOM.len());
+    }
+    let value = serde_json::from_reader(reader).context("Json Deserialization")?;
+    Ok(Parsed {
+        value,
+        warning: None,
+        source: None,
+    })
+}
+
+/// `value` as compact JSON, the way every scalar is shown. JSON numbers keep
+/// the digits they were written with, so a 30-digit ID isn't rounded to an
+/// `f64` or put in scientific notation, and `1.50` stays `1.50`.
+pub fn scalar_text(value: &Value) -> String {
+    // `arbitrary_precision` holds on to each number's text for this.
+    value.to_string()
+}
+
+/// `value` as pretty JSON, each level nested one `indent` further in.
+pub fn pretty_json(value: &Value, indent: &str) -> serde_json::Result<String> {
+    let mut out = Vec::new();
+    let formatter = PrettyFormatter::with_indent(indent.as_bytes());
+    value.serialize(&mut Serializer::with_formatter(&mut out, formatter))?;
+    // Nothing but UTF-8 is written.
+    Ok(String::from_utf8_lossy(&out).into_owned())
+}
+
+/// Converts a TOML document into a JSON value, keeping datetimes in their
+/// TOML string form. Arrays of tables become arrays of objects.
+fn toml_to_json(toml: toml::Value) -> Value {
+    match toml {
+        toml::Value::String(s) => Value::String(s),
+        toml::Value::Integer(i) => Value::from(i),
+        toml::Value::Float(f) => {
+            Number::from_f64(f).map_or_else(|| Value::String(f.to_string()), Value::Number)
+        }
+        toml::Value::Boolean(b) => Value::Bool(b),
+        toml::Value::Datetime(datetime) => Value::String(datetime.to_string()),
+        toml::Value::Array(arr) => arr.into_iter().map(toml_to_json).collect(),
+        toml::Value::Table(table) => Value::Object(
+            table
+                .into_iter()
+                .map(|(k, v)| (k, toml_to_json(v)))
+                .collect(),
+        ),
+    }
+}
+
+/// The 1-based line and byte column of `offset` into `text`.
+fn line_column(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset];
+    let line = before.matches('\n').count() + 1;
+    let column = before.len() - before.rfind('\n').map_or(0, |i| i + 1) + 1;
+    (line, column)
+}
+
+/// Builds a parse error that quotes the offending line of `text` with a
+/// caret under the 1-based `column`.
+fn located(text: &str, line: usize, column: usize, message: &dyn Display) -> anyhow::Error {
+    let Some(source) = text.lines().nth(line.saturating_sub(1)) else {
+        return anyhow!("{message}");
+    };
+    let number = line.to_string();
+    let gutter = " ".repeat(number.len());
+    let prefix = source.get(..column.saturating_sub(1)).unwrap_or(source);
+    let caret = " ".repeat(prefix.width());
+    anyhow!("{message}\n {number} | {source}\n {gutter} | {caret}^")
+}
+
+/// Converts a YAML document, whose aliases serde_yaml has already resolved,
+/// into a JSON value. Mapping keys that aren't strings are shown as compact
+/// JSON, and tags are dropped in favour of the tagged value.
+fn yaml_to_json(yaml: serde_yaml::Value) -> Value {
+    match yaml {
+        serde_yaml::Value::Null => Value::Null,
+        serde_yaml::Value::Bool(b) => Value::Bool(b),
+        serde_yaml::Value::Number(n) => {
+            if let Some(i) = n.as_i64() {
+                Value::from(i)
+            } else if let Some(u) = n.as_u64() {
+                Value::from(u)
+            } else {
+                // `.inf` and `.nan` have no JSON number form.
+                n.as_f64()
+                    .and_then(Number::from_f64)
+                    .map_or_else(|| Value::String(n.to_string()), Value::Number)
+            }
+        }
+        serde_yaml::Value::String(s) => Value::String(s),
+        serde_yaml::Value::Sequence(seq) => seq.into_iter().map(yaml_to_json).collect(),
+        serde_yaml::Value::Mapping(mapping) => {
+            let map: Map<String, Value> = mapping
+                .into_iter()
+                .map(|(k, v)| (yaml_key(k), yaml_to_json(v)))
+                .collect();
+            Value::Object(map)
+        }
+        serde_yaml::Value::Tagged(tagged) => yaml_to_json(tagged.value),
+    }
+}
+
+fn yaml_key(key: serde_yaml::Value) -> String {
+    match key {
+        serde_yaml::Value::String(s) => s,
+        serde_yaml::Value::Tagged(tagged) => yaml_key(tagged.value),
+        other => yaml_to_json(other).to_string(),
+    }
+}
+
+/// Parses each non-empty line on its own, collecting malformed lines as
+/// `line N: error` messages instead of stopping at the first one.
+fn parse_jsonl(text: &str) -> Result<(Vec<Value>, Vec<String>)> {
+    let mut records = Vec::new();
+    let mut errors = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(line) {
+            Ok(record) => records.push(record),
+            Err(e) => {
+                // serde_json places the location relative to this one line.
+                let message = e.to_string();
+                let message = message.split(" at line ").next().unwrap_or_default();
+                errors.push(format!("line {}: {message}", number + 1));
+            }
+        }
+    }
+    if records.is_empty() && !errors.is_empty() {
+        anyhow::bail!("Jsonl Deserialization: no valid records, {}", errors[0]);
+    }
+    Ok((records, errors))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn json5_allows_comments_and_unquoted_keys() {
+        let text = "// replicas\n{spec: {replicas: 3, name: 'web',},}";
+        let parsed = parse(text, Format::Json5).unwrap();
+        assert_eq!(
+            parsed.value,
+            json!({"spec": {"replicas": 3, "name": "web"}})
+        );
+    }
+
+    #[test]
+    fn json_with_comments_is_an_error() {
+        let error = parse("{\n  // replicas\n  \"a\": 1\n}", Format::Json)
+            .err()
+            .unwrap();
+        assert!(format!("{error:#}").contains("line 2 column 3"));
+    }
+
+    #[test]
+    fn json5_errors_point_into_the_text() {
+        let error = parse("{a:}", Format::Json5).err().unwrap();
+        assert!(format!("{error:#}").contains(" 1 | {a:}\n   |    ^"));
+    }
+
+    #[test]
+    fn numbers_keep_their_digits() {
+        let text =
+            r#"{"id": 12345678901234567890123, "price": 0.10000000000000000000001, "rate": 1.50}"#;
+        let value = parse(text, Format::Json).unwrap().value;
+        assert_eq!(scalar_text(&value["id"]), "12345678901234567890123");
+        assert_eq!(scalar_text(&value["price"]), "0.10000000000000000000001");
+        assert_eq!(scalar_text(&value["rate"]), "1.50");
+        let written = pretty_json(&value, "  ").unwrap();
+        assert_eq!(
+            written,
+            "{\n  \"id\": 12345678901234567890123,\n  \"price\": 0.10000000000000000000001,\n  \"rate\": 1.50\n}"
+        );
+        assert_eq!(parse(&written, Format::Json).unwrap().value, value);
+        assert_eq!(parse_reader(text.as_bytes()).unwrap().value, value);
+    }
+}
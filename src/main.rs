@@ -1,174 +1,3017 @@
 use std::{
-    env, fs,
-    io::{self, Write},
-    time::Duration,
+    env,
+    fs::{self, File},
+    io::{self, IsTerminal, Read, Write},
+    ops::Range,
+    panic,
+    path::Path,
+    process,
+    sync::mpsc::{self, RecvTimeoutError},
+    thread,
+    time::{Duration, Instant},
 };
 
 use anyhow::{Context, Result};
+use clap::{error::ErrorKind, CommandFactory};
 
 use serde_json::Value;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
 
 use crossterm::{
     cursor::{self, MoveTo, MoveToColumn, MoveToNextLine},
-    event::{poll, read, Event, KeyCode},
+    event::{
+        poll, read, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent,
+        KeyModifiers, MouseButton, MouseEventKind,
+    },
     execute, queue,
-    style::{Color, Print, ResetColor, SetBackgroundColor, SetForegroundColor},
+    style::{
+        Attribute, Color, Print, ResetColor, SetAttribute, SetBackgroundColor, SetForegroundColor,
+    },
     terminal, QueueableCommand,
 };
 
-use crate::state::{PanelSide, PanelState, ProgramState};
+use cjv::{
+    diff::{self, Change, DiffTree},
+    flat::FlatState,
+    load::{self, Format, Parsed},
+    path::{self, format_path, PathSegment},
+    source::SourceMap,
+    stats::DocStats,
+    table::detect_table_schema,
+    tree::TreeState,
+    watch::FileWatcher,
+    Case, KeyOrder, Layout, PanelSide, PanelState, Pattern, ProgramState, Snapshot, ViewKey,
+};
+
+use crate::{
+    cli::Cli,
+    clipboard::Clipboard,
+    keymap::{Action, Key, KeyMap},
+    theme::Theme,
+};
+
+mod cli;
+mod clipboard;
+mod keymap;
+mod session;
+mod theme;
+
+/// Entries moved per mouse wheel tick.
+const WHEEL_STEP: usize = 3;
+
+/// How long loading goes on before a spinner shows that it is.
+const SPINNER_DELAY: Duration = Duration::from_millis(200);
+
+const SPINNER_INTERVAL: Duration = Duration::from_millis(80);
+
+const SPINNER: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
 
-mod state;
+/// How often the file watcher is checked while waiting for input.
+const WATCH_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The screen size assumed when the terminal can't tell what it is.
+const FALLBACK_SIZE: (u16, u16) = (80, 24);
 
 fn main() -> Result<()> {
-    let args: Vec<String> = env::args().collect();
-    let path = &args[1];
-    let file = fs::read_to_string(path).context("File Input")?;
+    let options = Cli::parse_with_env();
+    if options.clear_state {
+        return session::clear();
+    }
+    // Crossterm falls back to /dev/tty for key events when stdin is not a
+    // terminal, so piped input leaves the event loop untouched.
+    let paths = options.paths();
+    if paths.contains(&None) && (io::stdin().is_terminal() || options.watch) {
+        Cli::command()
+            .error(
+                ErrorKind::MissingRequiredArgument,
+                "a file is needed unless input is piped",
+            )
+            .exit();
+    }
+
+    if options.diff && (paths.len() != 2 || paths.contains(&None)) {
+        Cli::command()
+            .error(ErrorKind::WrongNumberOfValues, "--diff compares two files")
+            .exit();
+    }
+
+    // Parse before the terminal changes mode so a syntax error is printed
+    // to a normal screen.
+    let documents = paths
+        .iter()
+        .map(|path| load_document(&options, *path, paths.len() > 1))
+        .collect::<Result<Vec<_>>>()?;
 
-    let mut stdout = io::stdout();
-    if let Err(e) = main_loop(&mut stdout, &file) {
+    if let Some(cli::Goto(goto)) = &options.goto {
+        if let Err(failed) = path::resolve(&documents[0].value, goto) {
+            let prefix = format_path(&goto[..failed]);
+            eprintln!("Error: no {} in {prefix}", goto[failed]);
+            process::exit(1);
+        }
+    }
+
+    if options.output {
+        let goto = options.goto.as_ref().map_or(&[][..], |goto| &goto.0);
+        // Checked above, so it resolves.
+        let node = path::resolve(&documents[0].value, goto).unwrap_or(&documents[0].value);
+        let json = if options.compact {
+            serde_json::to_string(node)?
+        } else {
+            load::pretty_json(node, &options.indent.0)?
+        };
+        writeln!(io::stdout(), "{json}")?;
+        return Ok(());
+    }
+
+    // Colors go by where the panels are drawn, which isn't stdout when
+    // that is captured.
+    let (mut stdout, terminal) = screen();
+    let theme = load_theme(&options, theme::color_enabled(terminal));
+
+    let keymap = KeyMap::load().unwrap_or_else(|e| {
+        eprintln!("Error: {e:#}");
+        process::exit(1);
+    });
+
+    // A panic would otherwise leave the terminal in raw mode on the
+    // alternate screen, taking its message with it.
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let _ = restore_terminal(&mut screen().0);
+        default_hook(info);
+    }));
+
+    if let Err(e) = main_loop(&mut stdout, &options, documents, &keymap, &theme) {
+        restore_terminal(&mut stdout)?;
+        println!("Error: {e:?}\r");
+    }
+
+    Ok(())
+}
+
+/// Loads the document at `path`, or stdin for `None`, exiting with the error
+/// if it can't be. With `--verbose`, says how long that took, naming the
+/// file if it is one of `several`.
+fn load_document(options: &Cli, path: Option<&str>, several: bool) -> Result<Parsed> {
+    let (format, stream, locate) = (options.format(path), options.stream, options.positions);
+    let owned = path.map(str::to_owned);
+    let (done, loaded) = mpsc::channel();
+    let loader = thread::spawn(move || {
+        let mut times = LoadTimes::default();
+        let parsed = load_input(owned.as_deref(), format, stream, locate, &mut times);
+        // Only fails once the spinner has given up on it.
+        let _ = done.send((parsed, times));
+    });
+    // Big files and slow pipes take a moment, so show that something is
+    // going on rather than seem to hang.
+    let mut wait = SPINNER_DELAY;
+    let mut frames = SPINNER.iter().cycle();
+    let mut spun = false;
+    let received = loop {
+        match loaded.recv_timeout(wait) {
+            Err(RecvTimeoutError::Timeout) if io::stderr().is_terminal() => {
+                let name = path.unwrap_or("stdin");
+                let frame = frames.next().unwrap_or(&' ');
+                execute!(
+                    io::stderr(),
+                    MoveToColumn(0),
+                    Print(format!("{frame} loading {name}…"))
+                )?;
+                wait = SPINNER_INTERVAL;
+                spun = true;
+            }
+            Err(RecvTimeoutError::Timeout) => wait = Duration::MAX,
+            received => break received,
+        }
+    };
+    if spun {
         execute!(
+            io::stderr(),
+            MoveToColumn(0),
+            terminal::Clear(terminal::ClearType::CurrentLine)
+        )?;
+    }
+    // Nothing received means the loader panicked, so pass that on.
+    let Ok((parsed, times)) = received else {
+        panic::resume_unwind(
+            loader
+                .join()
+                .expect_err("the loader sends unless it panics"),
+        );
+    };
+    let parsed = match parsed {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            eprintln!("Error: {e:#}");
+            process::exit(1);
+        }
+    };
+    if options.verbose {
+        let name = if several {
+            format!("{}: ", path.unwrap_or("stdin"))
+        } else {
+            String::new()
+        };
+        let nodes = DocStats::of(&parsed.value).nodes;
+        match times.read {
+            Some(read) => eprintln!(
+                "{name}read in {read:.1?}, parsed in {:.1?}, {nodes} nodes",
+                times.parse
+            ),
+            None => eprintln!(
+                "{name}read and parsed in {:.1?}, {nodes} nodes",
+                times.parse
+            ),
+        }
+    }
+    Ok(parsed)
+}
+
+/// An in-progress `/` search over the middle panel, or over the flat view
+/// while it is open.
+struct Search {
+    query: String,
+    /// Selection to return to if the search is cancelled.
+    origin: usize,
+    matched: bool,
+}
+
+impl Search {
+    /// Re-runs the query from where the search started.
+    fn update(&mut self, program_state: &mut ProgramState, flat: Option<&mut FlatState>) {
+        if let Some(flat) = flat {
+            flat.goto(self.origin);
+            let found = program_state
+                .pattern(&self.query)
+                .filter(|_| !self.query.is_empty())
+                .and_then(|pattern| flat.find(&pattern, 0, true));
+            if let Some(index) = found {
+                flat.goto(index);
+            }
+            // Flat rows all come from this document, so they resolve.
+            let _ = program_state.goto_path(flat.selected_path());
+            self.matched = found.is_some();
+            return;
+        }
+        program_state.goto(self.origin);
+        let found = program_state
+            .search(&self.query)
+            .filter(|_| !self.query.is_empty());
+        if let Some(index) = found {
+            program_state.goto(index);
+        }
+        self.matched = found.is_some();
+    }
+}
+
+/// An open document-wide fuzzy find, listing the paths that match.
+struct Finder {
+    query: String,
+    results: Vec<Vec<PathSegment>>,
+    selected: usize,
+}
+
+impl Finder {
+    fn update(&mut self, program_state: &ProgramState) {
+        self.results = program_state.find_all(&self.query);
+        self.selected = 0;
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        let last = self.results.len().saturating_sub(1);
+        self.selected = self.selected.saturating_add_signed(delta).min(last);
+    }
+}
+
+/// A full-screen, scrollable dump of one value, opened over the panels.
+struct Viewer<'a> {
+    value: &'a Value,
+    lines: Vec<PreviewLine>,
+    /// First line in view.
+    scroll: usize,
+}
+
+impl<'a> Viewer<'a> {
+    /// Lays out `value` for a screen `width` columns wide: containers in
+    /// full as pretty JSON indented by `indent`, and scalars wrapped at
+    /// words.
+    fn new(
+        value: &'a Value,
+        width: usize,
+        decode: bool,
+        indent: &str,
+        theme: &Theme,
+    ) -> Viewer<'a> {
+        let lines = match value {
+            Value::Object(_) | Value::Array(_) => {
+                let mut preview = Preview {
+                    lines: Vec::new(),
+                    max_rows: usize::MAX,
+                    decode,
+                    indent: &shown_indent(indent),
+                    theme,
+                };
+                preview.push_value("", Vec::new(), value, usize::MAX, false);
+                preview.lines
+            }
+            scalar => scalar_rows(scalar, width, decode, theme)
+                .into_iter()
+                .map(|row| vec![(row, theme.value_color(scalar))])
+                .collect(),
+        };
+        Viewer {
+            value,
+            lines,
+            scroll: 0,
+        }
+    }
+
+    fn scroll_by(&mut self, delta: isize, height: usize) {
+        let last = self.lines.len().saturating_sub(height);
+        self.scroll = self.scroll.saturating_add_signed(delta).min(last);
+    }
+}
+
+/// Why `run` stopped handling events for the current document.
+enum Outcome {
+    Quit,
+    Reload,
+    NextTab,
+    PrevTab,
+}
+
+/// An open document, and where its view was left while another tab is in
+/// front of it.
+struct Tab {
+    /// The file, or `None` for stdin or a diff.
+    path: Option<String>,
+    value: Value,
+    /// The files a `--diff` compares, old then new, and how they differ.
+    /// `value` is then the two merged.
+    diff: Option<(String, String, DiffTree)>,
+    /// Where each value starts in the file, with `--positions`.
+    source: Option<SourceMap>,
+    watcher: Option<FileWatcher>,
+    snapshot: Snapshot,
+    /// Where to put the view when the tab is first shown.
+    resume: Option<Vec<PathSegment>>,
+    /// Where the view was last left, once the tab has been shown.
+    left_at: Option<Vec<PathSegment>>,
+    /// Shown on the status line the next time the tab is.
+    message: Option<String>,
+}
+
+impl Tab {
+    /// What the tab strip calls the tab: the file as it was given.
+    fn label(&self) -> String {
+        if let Some((old, new, _)) = &self.diff {
+            return format!("{old} → {new}");
+        }
+        self.path.clone().unwrap_or_else(|| "stdin".to_owned())
+    }
+}
+
+fn main_loop(
+    stdout: &mut impl Write,
+    options: &Cli,
+    documents: Vec<Parsed>,
+    keymap: &KeyMap,
+    theme: &Theme,
+) -> Result<()> {
+    let mut tabs = Vec::with_capacity(documents.len());
+    let documents = if options.diff {
+        let files: Vec<String> = options
+            .paths()
+            .into_iter()
+            .flatten()
+            .map(str::to_owned)
+            .collect();
+        let mut documents = documents.into_iter();
+        if let (Some(old), Some(new), [old_path, new_path]) =
+            (documents.next(), documents.next(), &files[..])
+        {
+            let (value, changes, message) = compare(old, new);
+            tabs.push(Tab {
+                path: None,
+                value,
+                diff: Some((old_path.clone(), new_path.clone(), changes)),
+                source: None,
+                watcher: None,
+                snapshot: Snapshot::default(),
+                resume: options.goto.clone().map(|goto| goto.0),
+                left_at: None,
+                message: Some(message),
+            });
+        }
+        Vec::new()
+    } else {
+        documents
+    };
+    for (index, (parsed, path)) in documents.into_iter().zip(options.paths()).enumerate() {
+        let watcher = match path {
+            Some(path) if options.watch => Some(FileWatcher::new(Path::new(path))?),
+            _ => None,
+        };
+        // `--goto` is for the first document only.
+        let goto = options
+            .goto
+            .clone()
+            .map(|goto| goto.0)
+            .filter(|_| index == 0);
+        let resume = goto.or_else(|| {
+            path.filter(|_| !options.no_restore)
+                .and_then(|path| session::load(Path::new(path)))
+        });
+        tabs.push(Tab {
+            path: path.map(str::to_owned),
+            value: parsed.value,
+            diff: None,
+            source: parsed.source,
+            watcher,
+            snapshot: Snapshot::default(),
+            resume,
+            left_at: None,
+            message: parsed.warning,
+        });
+    }
+    let labels: Vec<String> = tabs.iter().map(Tab::label).collect();
+
+    // Where the panels can't be drawn, show the documents the only way
+    // left rather than fail.
+    let dumb = env::var("TERM").is_ok_and(|term| term == "dumb");
+    if dumb || terminal::enable_raw_mode().is_err() {
+        let values: Vec<&Value> = tabs.iter().map(|tab| &tab.value).collect();
+        // Nor can a dumb terminal show colors.
+        let plain = Theme::plain();
+        let indent = &options.indent.0;
+        return dump(&values, indent, if dumb { &plain } else { theme });
+    }
+    execute!(
+        stdout,
+        cursor::Hide,
+        terminal::EnterAlternateScreen,
+        EnableMouseCapture
+    )?;
+
+    let mut active = 0;
+    let mut clipboard = Clipboard::new();
+    loop {
+        let tab = &mut tabs[active];
+        let mut message = tab.message.take();
+        let size = terminal::size().unwrap_or(FALLBACK_SIZE);
+        let mut program_state = ProgramState::new(&tab.value, size);
+        program_state.set_columns(options.columns());
+        if let Some(widths) = &options.widths {
+            program_state.set_widths(&widths.0);
+        }
+        program_state.set_layout(options.layout);
+        program_state.set_compact(options.compact);
+        program_state.set_folding(options.fold, options.group_size);
+        program_state.set_decode_strings(!options.raw_strings);
+        program_state.set_preview_depth(options.preview_depth);
+        program_state.set_preview_values(options.preview_values);
+        program_state.set_indent(&options.indent.0);
+        program_state.set_only(options.only.as_deref());
+        program_state.restore(&tab.snapshot);
+        if let Some(resume) = tab.resume.take() {
+            // A path the document no longer has leaves the view at the root.
+            let _ = program_state.goto_path(&resume);
+        }
+        let outcome = run(
             stdout,
-            cursor::Show,
-            ResetColor,
-            terminal::LeaveAlternateScreen
+            &mut program_state,
+            tab.watcher.as_mut(),
+            &mut clipboard,
+            &mut message,
+            (&labels, active),
+            tab.diff.as_ref().map(|(_, _, changes)| changes),
+            tab.source.as_ref(),
+            keymap,
+            theme,
         )?;
-        terminal::disable_raw_mode()?;
-        println!("Error: {e:?}\r");
+        tab.snapshot = program_state.snapshot();
+        tab.left_at = Some(program_state.path_segments());
+        match outcome {
+            Outcome::Quit => break,
+            Outcome::Reload => match &mut tab.diff {
+                Some((old_path, new_path, changes)) => {
+                    let reloaded = [old_path.as_str(), new_path.as_str()].map(|path| {
+                        reload(
+                            Some(path),
+                            options.format(Some(path)),
+                            options.stream,
+                            false,
+                        )
+                    });
+                    match reloaded {
+                        [Ok(old), Ok(new)] => {
+                            let (value, reloaded_changes, message) = compare(old, new);
+                            tab.value = value;
+                            *changes = reloaded_changes;
+                            tab.message = Some(message);
+                        }
+                        [Err(e), _] | [_, Err(e)] => {
+                            let error = format!("{e:#}");
+                            tab.message = error.lines().next().map(str::to_owned);
+                        }
+                    }
+                }
+                None => {
+                    let path = tab.path.as_deref();
+                    match reload(
+                        path,
+                        options.format(path),
+                        options.stream,
+                        options.positions,
+                    ) {
+                        Ok(reloaded) => {
+                            tab.value = reloaded.value;
+                            tab.source = reloaded.source;
+                            tab.message =
+                                Some(reloaded.warning.unwrap_or_else(|| "reloaded".to_owned()));
+                        }
+                        Err(e) => {
+                            // Only the summary fits on the status line.
+                            let error = format!("{e:#}");
+                            tab.message = error.lines().next().map(str::to_owned);
+                        }
+                    }
+                }
+            },
+            Outcome::NextTab => active = (active + 1) % tabs.len(),
+            Outcome::PrevTab => active = (active + tabs.len() - 1) % tabs.len(),
+        }
     }
 
+    for tab in &tabs {
+        if let (Some(path), Some(left_at)) = (&tab.path, &tab.left_at) {
+            // Losing the position is no reason to fail on the way out.
+            let _ = session::save(Path::new(path), left_at);
+        }
+    }
+    restore_terminal(stdout)?;
+    for text in clipboard.unsent() {
+        // Kept off stdout when that is for the path alone.
+        if options.print_path_on_exit {
+            eprintln!("{text}");
+        } else {
+            println!("{text}");
+        }
+    }
+    if options.print_path_on_exit {
+        let path = tabs[active].left_at.as_deref().unwrap_or_default();
+        writeln!(io::stdout(), "{}", format_path(path))?;
+    }
     Ok(())
 }
 
-#[allow(clippy::too_many_lines)]
-fn main_loop(stdout: &mut io::Stdout, file: &str) -> Result<()> {
-    let value: Value = serde_json::from_str(file).context("Json Deserialization")?;
-    let mut program_state = ProgramState::new(&value, terminal::size()?);
+/// Where to draw, and whether it is a terminal: stdout, unless that is
+/// captured, as by `$(jmv …)`, in which case the terminal itself so that
+/// only what is printed on the way out ends up captured.
+fn screen() -> (Box<dyn Write + Send>, bool) {
+    if !io::stdout().is_terminal() {
+        if let Ok(tty) = File::options().write(true).open("/dev/tty") {
+            let terminal = tty.is_terminal();
+            return (Box::new(tty), terminal);
+        }
+    }
+    (Box::new(io::stdout()), io::stdout().is_terminal())
+}
 
-    execute!(stdout, cursor::Hide, terminal::EnterAlternateScreen)?;
-    terminal::enable_raw_mode()?;
+/// The theme given by `--theme`, otherwise the default one if `color`
+/// allows, with the selection options applied on top. Exits with the error
+/// if the theme file can't be loaded.
+fn load_theme(options: &Cli, color: bool) -> Theme {
+    let mut theme = match options.theme.as_deref() {
+        Some(path) => Theme::load(path).unwrap_or_else(|e| {
+            eprintln!("Error: {e:#}");
+            process::exit(1);
+        }),
+        None if color => Theme::default(),
+        None => Theme::plain(),
+    };
+    if options.select_reverse {
+        theme.reverse_selection = true;
+        theme.selection_bg = Color::Reset;
+        theme.selection_fg = Color::Reset;
+    }
+    theme.selection_bg = options.select_bg.unwrap_or(theme.selection_bg);
+    theme.selection_fg = options.select_fg.unwrap_or(theme.selection_fg);
+    theme.glyphs = options.glyphs;
+    theme
+}
+
+/// Prints `values` as pretty JSON in the colors of `theme`, through
+/// `$PAGER` when that is set, for terminals the panels can't be drawn on.
+fn dump(values: &[&Value], indent: &str, theme: &Theme) -> Result<()> {
+    let mut text = Vec::new();
+    for value in values {
+        let mut preview = Preview {
+            lines: Vec::new(),
+            max_rows: usize::MAX,
+            decode: false,
+            indent,
+            theme,
+        };
+        preview.push_value("", Vec::new(), value, usize::MAX, false);
+        for line in preview.lines {
+            for (span, color) in line {
+                // A plain theme leaves out escape codes altogether.
+                if color == Color::Reset {
+                    text.extend_from_slice(span.as_bytes());
+                } else {
+                    queue!(text, SetForegroundColor(color), Print(span), ResetColor)?;
+                }
+            }
+            text.push(b'\n');
+        }
+    }
+    let pager = env::var("PAGER").unwrap_or_default();
+    if pager.trim().is_empty() {
+        io::stdout().write_all(&text)?;
+        return Ok(());
+    }
+    // `less` shows colors only when told to.
+    let less = env::var_os("LESS").unwrap_or_else(|| "R".into());
+    let mut child = process::Command::new("sh")
+        .args(["-c", &pager])
+        .env("LESS", less)
+        .stdin(process::Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Running {pager}"))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // Quitting the pager before the end closes the pipe early.
+        let _ = stdin.write_all(&text);
+    }
+    child.wait().with_context(|| format!("Running {pager}"))?;
+    Ok(())
+}
+
+/// Merges two documents for a diff, returning the merged value, how they
+/// differ, and a summary for the status line along with any warnings.
+fn compare(old: Parsed, new: Parsed) -> (Value, DiffTree, String) {
+    let changes = diff::diff(&old.value, &new.value);
+    let mut message = match changes.differences() {
+        0 => "no differences".to_owned(),
+        1 => "1 difference".to_owned(),
+        count => format!("{count} differences"),
+    };
+    for warning in [old.warning, new.warning].into_iter().flatten() {
+        message += &format!("; {warning}");
+    }
+    (diff::merge(&old.value, &new.value), changes, message)
+}
+
+/// Puts the terminal back the way `main_loop` found it.
+fn restore_terminal(stdout: &mut impl Write) -> io::Result<()> {
+    execute!(
+        stdout,
+        cursor::Show,
+        ResetColor,
+        DisableMouseCapture,
+        terminal::LeaveAlternateScreen
+    )?;
+    terminal::disable_raw_mode()
+}
+
+/// How long loading the input took, for `--verbose`.
+#[derive(Default)]
+struct LoadTimes {
+    /// Reading the input in full, which a streamed parse doesn't do first.
+    read: Option<Duration>,
+    /// Parsing it, and reading it too when streamed.
+    parse: Duration,
+}
 
+/// Reads and parses the input file, or stdin when there is no `path`,
+/// noting how long that took in `times`. With `stream`, JSON is parsed as
+/// it is read rather than read in full first, and with `locate` where each
+/// value starts is noted.
+fn load_input(
+    path: Option<&str>,
+    format: Format,
+    stream: bool,
+    locate: bool,
+    times: &mut LoadTimes,
+) -> Result<Parsed> {
+    let started = Instant::now();
+    if stream && format == Format::Json {
+        let parsed = match path {
+            None => load::parse_reader(io::stdin().lock()),
+            Some(path) => load::parse_reader(fs::File::open(path).context("File Input")?),
+        };
+        times.parse = started.elapsed();
+        return parsed;
+    }
+    let bytes = match path {
+        None => {
+            let mut bytes = Vec::new();
+            io::stdin().read_to_end(&mut bytes).context("Stdin Input")?;
+            bytes
+        }
+        Some(path) => fs::read(path).context("File Input")?,
+    };
+    times.read = Some(started.elapsed());
+    let started = Instant::now();
+    let parsed = load::parse_bytes(bytes, format, locate);
+    times.parse = started.elapsed();
+    parsed
+}
+
+fn reload(path: Option<&str>, format: Format, stream: bool, locate: bool) -> Result<Parsed> {
+    let path = path.context("Cannot reload stdin")?;
+    load_input(
+        Some(path),
+        format,
+        stream,
+        locate,
+        &mut LoadTimes::default(),
+    )
+}
+
+/// What a letter typed next is taken as.
+#[derive(Copy, Clone)]
+enum Letter {
+    /// The first letter of a key to jump to.
+    Jump,
+    /// The name to mark the selection with.
+    Mark,
+    /// The name of a mark to go back to.
+    GotoMark,
+}
+
+/// What `Ui::handle` leaves for `run` to do.
+enum Next {
+    Draw,
+    /// Draw only if the view, the status line or the typed count changed.
+    DrawIfChanged,
+    Stop(Outcome),
+}
+
+/// An event as the panels, the tree view and the viewer take it, once any
+/// open prompt has had its turn.
+enum Input {
+    /// What the keys just typed are bound to, repeated by the count typed
+    /// before them.
+    Actions(Vec<Action>, usize),
+    ScrollDown,
+    ScrollUp,
+    Click {
+        column: u16,
+        row: u16,
+    },
+    /// Any other mouse event, such as motion.
+    Mouse,
+    /// Anything else, such as a resize.
+    Other,
+}
+
+/// Everything opened over or alongside the panels, and keys typed toward
+/// what comes next.
+struct Ui<'a> {
+    keymap: &'a KeyMap,
+    theme: &'a Theme,
+    /// Labels of every open tab, and which of them is in front.
+    tabs: (&'a [String], usize),
+    /// How the files differ, when the document is a `--diff` of two.
+    diff: Option<&'a DiffTree>,
+    /// Where each value starts in the file, with `--positions`.
+    source: Option<&'a SourceMap>,
+    /// Keys of a sequence typed so far.
+    pending: Vec<Key>,
+    count: usize,
+    search: Option<Search>,
+    finder: Option<Finder>,
+    filtering: Option<String>,
+    command: Option<String>,
+    /// Waiting for a letter, and what it is for.
+    letter: Option<Letter>,
+    show_help: bool,
+    /// Lays out arrays of objects as tables in place of the panels.
+    table: bool,
+    tree: Option<TreeState<'a>>,
+    flat: Option<FlatState<'a>>,
+    viewer: Option<Viewer<'a>>,
+    /// The view the tree last followed, so moves made outside of it (a
+    /// search, a `:` jump) are revealed in the tree too.
+    tree_key: Option<ViewKey>,
+}
+
+#[allow(clippy::too_many_arguments)]
+fn run<'a>(
+    stdout: &mut impl Write,
+    program_state: &mut ProgramState<'a>,
+    mut watcher: Option<&mut FileWatcher>,
+    clipboard: &mut Clipboard,
+    message: &mut Option<String>,
+    tabs: (&'a [String], usize),
+    diff: Option<&'a DiffTree>,
+    source: Option<&'a SourceMap>,
+    keymap: &'a KeyMap,
+    theme: &'a Theme,
+) -> Result<Outcome> {
+    let mut ui = Ui::new(keymap, theme, tabs, diff, source);
+    let mut dirty = true;
     loop {
+        if dirty {
+            ui.draw(stdout, program_state, message.as_deref())?;
+        }
+
+        let event = match watcher.as_deref_mut() {
+            Some(watcher) => loop {
+                if poll(WATCH_INTERVAL)? {
+                    break read()?;
+                }
+                if watcher.changed() {
+                    return Ok(Outcome::Reload);
+                }
+            },
+            None => read()?,
+        };
+        let before = ui.status_key(program_state, message);
+        if let Event::Key(_) = event {
+            *message = None;
+        }
+        if let Event::Resize(x, y) = event {
+            let (_, new_size) = flush_resize_events((x, y));
+            ui.resize(program_state, new_size);
+            // Terminals reflow on resize, so start the next frame clean.
+            stdout.queue(terminal::Clear(terminal::ClearType::All))?;
+        }
+        dirty = match ui.handle(&event, program_state, clipboard, message)? {
+            Next::Draw => true,
+            // Skip redrawing for keys that had no effect and for mouse motion.
+            Next::DrawIfChanged => ui.is_open() || before != ui.status_key(program_state, message),
+            Next::Stop(outcome) => return Ok(outcome),
+        };
+    }
+}
+
+impl<'a> Ui<'a> {
+    fn new(
+        keymap: &'a KeyMap,
+        theme: &'a Theme,
+        tabs: (&'a [String], usize),
+        diff: Option<&'a DiffTree>,
+        source: Option<&'a SourceMap>,
+    ) -> Ui<'a> {
+        Ui {
+            keymap,
+            theme,
+            tabs,
+            diff,
+            source,
+            pending: Vec::new(),
+            count: 0,
+            search: None,
+            finder: None,
+            filtering: None,
+            command: None,
+            letter: None,
+            show_help: false,
+            table: false,
+            tree: None,
+            flat: None,
+            viewer: None,
+            tree_key: None,
+        }
+    }
+
+    /// Whether anything is open that an event could change without the
+    /// view telling.
+    fn is_open(&self) -> bool {
+        self.search.is_some()
+            || self.finder.is_some()
+            || self.filtering.is_some()
+            || self.command.is_some()
+            || self.letter.is_some()
+            || self.show_help
+            || self.tree.is_some()
+            || self.flat.is_some()
+            || self.viewer.is_some()
+    }
+
+    /// The columns of the table to show in place of the panels, if tables
+    /// are on and the middle panel lists an array of objects entry by entry
+    /// rather than in groups.
+    fn table_columns(&self, program_state: &ProgramState) -> Option<Vec<String>> {
+        if !self.table || program_state.root_is_scalar() {
+            return None;
+        }
+        let panel = program_state.panel_state(PanelSide::Middle)?;
+        let (_, first) = panel.rows(0, 1).pop()?;
+        first?;
+        detect_table_schema(program_state.current())
+    }
+
+    fn status_key(
+        &self,
+        program_state: &ProgramState,
+        message: &Option<String>,
+    ) -> (ViewKey, Option<String>, usize, Vec<Key>) {
+        let key = program_state.view_key();
+        (key, message.clone(), self.count, self.pending.clone())
+    }
+
+    fn draw(
+        &mut self,
+        stdout: &mut impl Write,
+        program_state: &ProgramState,
+        message: Option<&str>,
+    ) -> Result<()> {
+        let theme = self.theme;
+        if let Some(active) = &mut self.tree {
+            let key = program_state.view_key();
+            if self.tree_key.as_ref() != Some(&key) {
+                active.reveal(&program_state.path_segments());
+                self.tree_key = Some(key);
+            }
+        }
+        // Built in memory and written at once, overwriting every cell
+        // in place rather than clearing first, so nothing flickers.
+        let mut frame = Vec::new();
+        render_header(&mut frame, program_state, self.tabs, theme)?;
+        let mut status = match (&self.search, &self.finder, &self.filtering, &self.command) {
+            _ if self.letter.is_some() => match self.letter {
+                Some(Letter::Mark) => "mark: ".to_owned(),
+                Some(Letter::GotoMark) => "go to mark: ".to_owned(),
+                Some(Letter::Jump) | None => "jump to: ".to_owned(),
+            },
+            (Some(search), _, _, _) => format!(
+                "/{}{}",
+                search.query,
+                query_note(program_state, Some(&search.query))
+            ),
+            (_, Some(finder), _, _) => {
+                format!("find: {}{}", finder.query, query_note(program_state, None))
+            }
+            (_, _, Some(filter), _) => {
+                format!(
+                    "filter: {filter}{}",
+                    query_note(program_state, Some(filter))
+                )
+            }
+            (_, _, _, Some(command)) => format!(":{command}"),
+            _ => match (message, program_state.filter(), program_state.only()) {
+                (Some(message), _, _) => message.to_owned(),
+                (None, Some(filter), _) => format!("filter: {filter}"),
+                (None, None, Some(only)) => format!("only: {only}"),
+                (None, None, None) => String::new(),
+            },
+        };
+        if self.count > 0 {
+            status += &format!(" {}", self.count);
+        }
+        if !self.pending.is_empty() {
+            if self.count == 0 {
+                status.push(' ');
+            }
+            status.extend(self.pending.iter().map(Key::to_string));
+        }
+        if let Some(viewer) = &self.viewer {
+            status += &render_viewer(&mut frame, program_state, viewer)?;
+        } else if let Some(finder) = &self.finder {
+            status += &render_finder(&mut frame, program_state, finder, theme)?;
+        } else if let Some(tree) = &self.tree {
+            status += &render_tree(&mut frame, program_state, tree, theme)?;
+        } else if let Some(flat) = &self.flat {
+            let search_matched = self.search.as_ref().is_some_and(|search| search.matched);
+            status += &render_flat(&mut frame, program_state, flat, search_matched, theme)?;
+        } else if program_state.root_is_scalar() {
+            status += &render_scalar_root(&mut frame, program_state, theme)?;
+        } else if let Some(columns) = self.table_columns(program_state) {
+            status += &render_table(&mut frame, program_state, &columns, theme)?;
+        } else {
+            let search_matched = self.search.as_ref().is_some_and(|search| search.matched);
+            // Whatever is being searched for or filtered by is picked out
+            // in the middle panel keys.
+            let query = self
+                .search
+                .as_ref()
+                .map(|search| search.query.as_str())
+                .filter(|query| !query.is_empty())
+                .or(program_state.filter());
+            let pattern = query.and_then(|query| program_state.pattern(query));
+            status += &render_view(
+                &mut frame,
+                program_state,
+                search_matched,
+                pattern.as_ref(),
+                self.diff,
+                self.source,
+                theme,
+            )?;
+        }
+        render_status(&mut frame, program_state, &status, theme)?;
+        if self.show_help {
+            render_help(&mut frame, program_state, self.keymap, theme)?;
+        }
+
+        stdout.write_all(&frame)?;
+        stdout.flush()?;
+        Ok(())
+    }
+
+    fn resize(&mut self, program_state: &mut ProgramState, size: (u16, u16)) {
+        program_state.resize(size);
+        if let Some(active) = &mut self.tree {
+            active.resize(program_state.view_height());
+        }
+        if let Some(active) = &mut self.flat {
+            active.resize(program_state.view_height());
+        }
+        if let Some(active) = &mut self.viewer {
+            let scroll = active.scroll;
+            let width = usize::from(size.0).saturating_sub(1);
+            let decode = program_state.decode_strings();
+            let indent = program_state.indent();
+            *active = Viewer::new(active.value, width, decode, indent, self.theme);
+            active.scroll_by(
+                scroll.try_into().unwrap_or(isize::MAX),
+                program_state.view_height(),
+            );
+        }
+    }
+
+    fn handle(
+        &mut self,
+        event: &Event,
+        program_state: &mut ProgramState<'a>,
+        clipboard: &mut Clipboard,
+        message: &mut Option<String>,
+    ) -> Result<Next> {
+        // Raw mode turns Ctrl-c into a key rather than an interrupt, so it
+        // quits from here whatever is open and however keys are bound.
+        if let Event::Key(KeyEvent {
+            code: KeyCode::Char('c'),
+            modifiers: KeyModifiers::CONTROL,
+            ..
+        }) = event
+        {
+            return Ok(Next::Stop(Outcome::Quit));
+        }
+        if self.show_help {
+            if let Event::Key(_) = event {
+                self.show_help = false;
+            }
+            return Ok(Next::Draw);
+        }
+        if let Event::Key(key) = event {
+            if self.prompt_key(*key, program_state, message) {
+                return Ok(Next::Draw);
+            }
+        } else if self.finder.is_some()
+            || self.search.is_some()
+            || self.filtering.is_some()
+            || self.command.is_some()
+            || self.letter.is_some()
+        {
+            return Ok(Next::Draw);
+        }
+
+        let Some(input) = self.input(event) else {
+            return Ok(Next::Draw);
+        };
+        if self.viewer.is_some() {
+            self.view_input(&input, program_state);
+            return Ok(Next::Draw);
+        }
+        if self.tree_input(&input, program_state) || self.flat_input(&input, program_state, message)
+        {
+            return Ok(Next::Draw);
+        }
+        self.panel_input(&input, program_state, clipboard, message)
+    }
+
+    /// Turns `event` into input, or `None` while it is only part of a count
+    /// or of a key sequence.
+    fn input(&mut self, event: &Event) -> Option<Input> {
+        let key = match event {
+            Event::Key(key) => *key,
+            Event::Mouse(mouse) => {
+                // Only keys are repeated, and anything else drops the count.
+                self.count = 0;
+                return Some(match mouse.kind {
+                    MouseEventKind::ScrollDown => Input::ScrollDown,
+                    MouseEventKind::ScrollUp => Input::ScrollUp,
+                    MouseEventKind::Down(MouseButton::Left) => Input::Click {
+                        column: mouse.column,
+                        row: mouse.row,
+                    },
+                    _ => Input::Mouse,
+                });
+            }
+            _ => {
+                self.count = 0;
+                return Some(Input::Other);
+            }
+        };
+        if let (
+            None,
+            KeyEvent {
+                code: KeyCode::Char(c @ '0'..='9'),
+                modifiers: KeyModifiers::NONE,
+                ..
+            },
+        ) = (&self.viewer, key)
+        {
+            let digit = c.to_digit(10).map_or(0, |d| d as usize);
+            self.count = self.count.saturating_mul(10).saturating_add(digit);
+            return None;
+        }
+        let actions = self.keymap.press(&mut self.pending, key.into());
+        if !self.pending.is_empty() {
+            // Halfway through a sequence, which the count is kept for.
+            return None;
+        }
+        let repeat = std::mem::take(&mut self.count).max(1);
+        Some(Input::Actions(actions, repeat))
+    }
+
+    /// Edits whichever prompt is open with `key`, returning whether there
+    /// was one.
+    fn prompt_key(
+        &mut self,
+        key: KeyEvent,
+        program_state: &mut ProgramState,
+        message: &mut Option<String>,
+    ) -> bool {
+        if let Some(active) = &mut self.finder {
+            match key.code {
+                KeyCode::Esc => self.finder = None,
+                KeyCode::Enter => {
+                    if let Some(path) = active.results.get(active.selected) {
+                        // Results come from this document, so they resolve.
+                        let _ = program_state.goto_path(path);
+                    }
+                    self.finder = None;
+                }
+                KeyCode::Down => active.move_selection(1),
+                KeyCode::Up => active.move_selection(-1),
+                KeyCode::Char('n') if key.modifiers == KeyModifiers::CONTROL => {
+                    active.move_selection(1);
+                }
+                KeyCode::Char('p') if key.modifiers == KeyModifiers::CONTROL => {
+                    active.move_selection(-1);
+                }
+                KeyCode::Char('c') if key.modifiers == KeyModifiers::ALT => {
+                    program_state.set_case(program_state.case().next());
+                    active.update(program_state);
+                }
+                KeyCode::Backspace => {
+                    active.query.pop();
+                    active.update(program_state);
+                }
+                KeyCode::Char(c) => {
+                    active.query.push(c);
+                    active.update(program_state);
+                }
+                _ => {}
+            }
+        } else if let Some(active) = &mut self.search {
+            match key.code {
+                KeyCode::Esc => {
+                    match &mut self.flat {
+                        Some(flat) => {
+                            flat.goto(active.origin);
+                            let _ = program_state.goto_path(flat.selected_path());
+                        }
+                        None => program_state.goto(active.origin),
+                    }
+                    self.search = None;
+                }
+                KeyCode::Enter => {
+                    if !active.query.is_empty() {
+                        program_state.set_last_query(active.query.clone());
+                    }
+                    self.search = None;
+                }
+                KeyCode::Char('c') if key.modifiers == KeyModifiers::ALT => {
+                    program_state.set_case(program_state.case().next());
+                    active.update(program_state, self.flat.as_mut());
+                }
+                KeyCode::Char('r') if key.modifiers == KeyModifiers::ALT => {
+                    program_state.toggle_regex();
+                    active.update(program_state, self.flat.as_mut());
+                }
+                KeyCode::Backspace => {
+                    active.query.pop();
+                    active.update(program_state, self.flat.as_mut());
+                }
+                KeyCode::Char(c) => {
+                    active.query.push(c);
+                    active.update(program_state, self.flat.as_mut());
+                }
+                _ => {}
+            }
+        } else if let Some(active) = &mut self.filtering {
+            match key.code {
+                KeyCode::Esc => {
+                    program_state.set_filter(None);
+                    self.filtering = None;
+                }
+                KeyCode::Enter => self.filtering = None,
+                KeyCode::Char('c') if key.modifiers == KeyModifiers::ALT => {
+                    program_state.set_case(program_state.case().next());
+                }
+                KeyCode::Char('r') if key.modifiers == KeyModifiers::ALT => {
+                    program_state.toggle_regex();
+                }
+                KeyCode::Backspace => {
+                    active.pop();
+                    program_state.set_filter(Some(active.clone()));
+                }
+                KeyCode::Char(c) => {
+                    active.push(c);
+                    program_state.set_filter(Some(active.clone()));
+                }
+                _ => {}
+            }
+        } else if let Some(active) = &mut self.command {
+            match key.code {
+                KeyCode::Esc => self.command = None,
+                KeyCode::Enter => {
+                    *message = run_command(program_state, active);
+                    self.command = None;
+                }
+                KeyCode::Backspace => {
+                    active.pop();
+                }
+                KeyCode::Char(c) => active.push(c),
+                _ => {}
+            }
+        } else if let Some(letter) = self.letter.take() {
+            // Any other key just gives up on it.
+            if let (KeyCode::Char(c), KeyModifiers::NONE | KeyModifiers::SHIFT) =
+                (key.code, key.modifiers)
+            {
+                *message = match letter {
+                    Letter::Jump if !program_state.jump_to_letter(c) => {
+                        Some(format!("no key starting with {c}"))
+                    }
+                    Letter::Jump => None,
+                    Letter::Mark => {
+                        program_state.set_mark(c);
+                        Some(format!("marked {c}"))
+                    }
+                    Letter::GotoMark => goto_mark(program_state, c),
+                };
+            }
+        } else {
+            return false;
+        }
+        true
+    }
+
+    fn view_input(&mut self, input: &Input, program_state: &ProgramState) {
+        let Some(active) = &mut self.viewer else {
+            return;
+        };
+        let height = program_state.view_height();
+        let rows = |rows: usize| isize::try_from(rows).unwrap_or(isize::MAX);
+        let half_page = rows((height / 2).max(1));
+        let full_page = rows(height.max(1));
+        match input {
+            Input::Actions(actions, _) => match actions.first() {
+                Some(Action::Quit | Action::Cancel | Action::View) => self.viewer = None,
+                Some(Action::Down) => active.scroll_by(1, height),
+                Some(Action::Up) => active.scroll_by(-1, height),
+                Some(Action::HalfPageDown) => active.scroll_by(half_page, height),
+                Some(Action::HalfPageUp) => active.scroll_by(-half_page, height),
+                Some(Action::PageDown) => active.scroll_by(full_page, height),
+                Some(Action::PageUp) => active.scroll_by(-full_page, height),
+                Some(Action::First) => active.scroll = 0,
+                Some(Action::Last) => active.scroll_by(isize::MAX, height),
+                _ => {}
+            },
+            Input::ScrollDown => active.scroll_by(rows(WHEEL_STEP), height),
+            Input::ScrollUp => active.scroll_by(-rows(WHEEL_STEP), height),
+            Input::Click { .. } | Input::Mouse | Input::Other => {}
+        }
+    }
+
+    /// Moves about the tree view if it is open and `input` is for it,
+    /// returning whether it was.
+    fn tree_input(&mut self, input: &Input, program_state: &mut ProgramState) -> bool {
+        let Some(active) = &mut self.tree else {
+            return false;
+        };
+        let height = program_state.view_height();
+        let half_page = (height / 2).max(1);
+        let full_page = height.max(1);
+        match input {
+            Input::Actions(actions, repeat) => match actions.first() {
+                Some(Action::Down) => active.inc_index(*repeat),
+                Some(Action::Up) => active.dec_index(*repeat),
+                Some(Action::Descend) => (0..*repeat).for_each(|_| active.expand()),
+                Some(Action::Ascend) => (0..*repeat).for_each(|_| active.collapse()),
+                Some(Action::ToggleNode) => active.toggle(),
+                Some(Action::HalfPageDown) => active.inc_index(half_page),
+                Some(Action::HalfPageUp) => active.dec_index(half_page),
+                Some(Action::PageDown) => active.inc_index(full_page),
+                Some(Action::PageUp) => active.dec_index(full_page),
+                Some(Action::First) => active.goto_first(),
+                Some(Action::Last) => active.goto_last(),
+                _ => return false,
+            },
+            Input::ScrollDown => active.inc_index(WHEEL_STEP),
+            Input::ScrollUp => active.dec_index(WHEEL_STEP),
+            Input::Click { row, .. } if *row > 0 => active.select_row(usize::from(row - 1)),
+            Input::Click { .. } | Input::Mouse => {}
+            Input::Other => return false,
+        }
+        // Tree rows all come from this document, so they resolve.
+        let _ = program_state.goto_path(active.selected_path());
+        self.tree_key = Some(program_state.view_key());
+        true
+    }
+
+    /// Moves about the flat view if it is open and `input` is for it,
+    /// returning whether it was. The panels follow the selection.
+    fn flat_input(
+        &mut self,
+        input: &Input,
+        program_state: &mut ProgramState,
+        message: &mut Option<String>,
+    ) -> bool {
+        let Some(active) = &mut self.flat else {
+            return false;
+        };
+        let height = program_state.view_height();
+        let half_page = (height / 2).max(1);
+        let full_page = height.max(1);
+        match input {
+            Input::Actions(actions, repeat) => match actions.first() {
+                Some(Action::Down) => active.inc_index(*repeat),
+                Some(Action::Up) => active.dec_index(*repeat),
+                Some(Action::HalfPageDown) => active.inc_index(half_page),
+                Some(Action::HalfPageUp) => active.dec_index(half_page),
+                Some(Action::PageDown) => active.inc_index(full_page),
+                Some(Action::PageUp) => active.dec_index(full_page),
+                Some(Action::First) => active.goto_first(),
+                Some(Action::Last) => active.goto_last(),
+                Some(action @ (Action::NextMatch | Action::PrevMatch)) => {
+                    let found = program_state
+                        .last_query()
+                        .and_then(|query| program_state.pattern(query))
+                        .and_then(|pattern| active.find(&pattern, 1, *action == Action::NextMatch));
+                    match found {
+                        Some(index) => active.goto(index),
+                        None => *message = Some("no matches".to_owned()),
+                    }
+                }
+                // Leaves the panels on the selected leaf.
+                Some(Action::Descend | Action::ToggleNode | Action::Cancel) => self.flat = None,
+                _ => return false,
+            },
+            Input::ScrollDown => active.inc_index(WHEEL_STEP),
+            Input::ScrollUp => active.dec_index(WHEEL_STEP),
+            Input::Click { row, .. } if *row > 0 => active.select_row(usize::from(row - 1)),
+            Input::Click { .. } | Input::Mouse => {}
+            Input::Other => return false,
+        }
+        if let Some(active) = &self.flat {
+            // Flat rows all come from this document, so they resolve.
+            let _ = program_state.goto_path(active.selected_path());
+        }
+        true
+    }
+
+    fn panel_input(
+        &mut self,
+        input: &Input,
+        program_state: &mut ProgramState<'a>,
+        clipboard: &mut Clipboard,
+        message: &mut Option<String>,
+    ) -> Result<Next> {
+        let (actions, repeat) = match input {
+            Input::Actions(actions, repeat) => (actions, *repeat),
+            Input::ScrollDown => {
+                program_state.inc_index(WHEEL_STEP);
+                return Ok(Next::DrawIfChanged);
+            }
+            Input::ScrollUp => {
+                program_state.dec_index(WHEEL_STEP);
+                return Ok(Next::DrawIfChanged);
+            }
+            Input::Click { row, .. } if self.table_columns(program_state).is_some() => {
+                // Below the row of column names.
+                if let (Some(panel), Some(row)) = (
+                    program_state.panel_state(PanelSide::Middle),
+                    usize::from(*row).checked_sub(2),
+                ) {
+                    let position = table_offset(&panel, program_state.view_height()) + row;
+                    program_state.select_position(position);
+                }
+                return Ok(Next::DrawIfChanged);
+            }
+            Input::Click { column, row } => {
+                if let Some(side) = program_state.panel_side_at(*column, *row) {
+                    program_state.select_at(side, *row);
+                }
+                return Ok(Next::DrawIfChanged);
+            }
+            Input::Mouse | Input::Other => return Ok(Next::DrawIfChanged),
+        };
+        let height = program_state.list_height();
+        let half_page = (height / 2).max(1);
+        let full_page = height.max(1);
+        // Only the tree has nodes to toggle, so a key bound to that as well
+        // as something else does the other thing out here.
+        let Some(action) = actions.iter().find(|action| **action != Action::ToggleNode) else {
+            return Ok(Next::DrawIfChanged);
+        };
+        match action {
+            Action::Tree if program_state.root_is_scalar() => {
+                *message = Some("nothing to expand in a scalar".to_owned());
+                return Ok(Next::Draw);
+            }
+            Action::Tree => {
+                self.flat = None;
+                self.tree = match self.tree {
+                    Some(_) => None,
+                    None => Some(TreeState::new(
+                        program_state.root(),
+                        &program_state.path_segments(),
+                        program_state.view_height(),
+                    )),
+                };
+                self.tree_key = Some(program_state.view_key());
+                return Ok(Next::Draw);
+            }
+            Action::Flat if program_state.root_is_scalar() => {
+                *message = Some("no leaves below a scalar".to_owned());
+                return Ok(Next::Draw);
+            }
+            Action::Flat => {
+                self.tree = None;
+                self.flat = match self.flat {
+                    Some(_) => None,
+                    None => Some(FlatState::new(
+                        program_state.root(),
+                        &program_state.path_segments(),
+                        program_state.view_height(),
+                    )),
+                };
+                return Ok(Next::Draw);
+            }
+            Action::Table => {
+                self.table = !self.table;
+                *message = Some(
+                    if self.table {
+                        "arrays of objects as tables"
+                    } else {
+                        "arrays of objects as lists"
+                    }
+                    .to_owned(),
+                );
+                return Ok(Next::Draw);
+            }
+            Action::Quit => return Ok(Next::Stop(Outcome::Quit)),
+            Action::NextTab => return Ok(Next::Stop(Outcome::NextTab)),
+            Action::PrevTab => return Ok(Next::Stop(Outcome::PrevTab)),
+            Action::Reload => return Ok(Next::Stop(Outcome::Reload)),
+            Action::Search => {
+                self.search = Some(Search {
+                    query: String::new(),
+                    origin: self
+                        .flat
+                        .as_ref()
+                        .map_or(program_state.index(), FlatState::index),
+                    matched: false,
+                });
+            }
+            Action::Find => {
+                self.finder = Some(Finder {
+                    query: String::new(),
+                    results: Vec::new(),
+                    selected: 0,
+                });
+            }
+            Action::YankPath => {
+                let path = program_state.current_path();
+                *message = Some(if clipboard.copy(path.clone()) {
+                    format!("copied {path}")
+                } else {
+                    "no clipboard, path printed on exit".to_owned()
+                });
+            }
+            Action::YankValue => {
+                let json =
+                    load::pretty_json(program_state.current_subtree(), program_state.indent())?;
+                let bytes = json.len();
+                *message = Some(if clipboard.copy(json) {
+                    format!("copied {bytes} bytes")
+                } else {
+                    "no clipboard, value printed on exit".to_owned()
+                });
+            }
+            Action::Command => self.command = Some(String::new()),
+            Action::View => {
+                let width = usize::from(program_state.size().0).saturating_sub(1);
+                let decode = program_state.decode_strings();
+                self.viewer = Some(Viewer::new(
+                    program_state.current_subtree(),
+                    width,
+                    decode,
+                    program_state.indent(),
+                    self.theme,
+                ));
+            }
+            Action::Filter => {
+                self.filtering = Some(program_state.filter().unwrap_or_default().to_owned());
+            }
+            Action::Cancel => {
+                program_state.set_filter(None);
+                program_state.set_only(None);
+            }
+            Action::NextMatch => {
+                if !program_state.next_match() {
+                    *message = Some("no matches".to_owned());
+                }
+            }
+            Action::PrevMatch => {
+                if !program_state.prev_match() {
+                    *message = Some("no matches".to_owned());
+                }
+            }
+            Action::Jump => self.letter = Some(Letter::Jump),
+            Action::Mark => self.letter = Some(Letter::Mark),
+            Action::GotoMark => self.letter = Some(Letter::GotoMark),
+            Action::Help => self.show_help = true,
+            Action::Down => program_state.inc_index(repeat),
+            Action::Up => program_state.dec_index(repeat),
+            Action::Descend => program_state.push_path(repeat),
+            Action::Ascend => program_state.pop_path(repeat),
+            Action::HalfPageDown => program_state.inc_index(half_page),
+            Action::HalfPageUp => program_state.dec_index(half_page),
+            Action::PageDown => program_state.inc_index(full_page),
+            Action::PageUp => program_state.dec_index(full_page),
+            Action::First => program_state.goto_first(),
+            Action::Last => program_state.goto_last(),
+            Action::Wrap => program_state.toggle_wrap(),
+            Action::Sort => {
+                let key_order = program_state.key_order().next();
+                program_state.set_key_order(key_order);
+                *message = Some(
+                    match key_order {
+                        KeyOrder::Document => "keys in document order",
+                        KeyOrder::Ascending => "keys sorted a-z",
+                        KeyOrder::Descending => "keys sorted z-a",
+                    }
+                    .to_owned(),
+                );
+            }
+            Action::Reverse => {
+                program_state.toggle_reverse_arrays();
+                *message = Some(
+                    if program_state.reverse_arrays() {
+                        "arrays last entry first"
+                    } else {
+                        "arrays in document order"
+                    }
+                    .to_owned(),
+                );
+            }
+            Action::Size => {
+                program_state.toggle_show_size();
+                *message = Some(
+                    if program_state.show_size() {
+                        "showing sizes"
+                    } else {
+                        "sizes hidden"
+                    }
+                    .to_owned(),
+                );
+            }
+            Action::Deeper => {
+                let depth = program_state.preview_depth().saturating_add(repeat);
+                program_state.set_preview_depth(depth);
+                *message = Some(format!("preview depth {depth}"));
+            }
+            Action::Shallower => {
+                let depth = program_state.preview_depth().saturating_sub(repeat);
+                program_state.set_preview_depth(depth);
+                *message = Some(format!("preview depth {depth}"));
+            }
+            Action::ToggleNode => {}
+        }
+        Ok(Next::DrawIfChanged)
+    }
+}
+
+/// Goes back to the selection marked `name`, returning what went wrong if
+/// it couldn't, as when a reload took away the path it was at.
+fn goto_mark(program_state: &mut ProgramState, name: char) -> Option<String> {
+    let Some(path) = program_state.mark(name).map(<[PathSegment]>::to_vec) else {
+        return Some(format!("no mark {name}"));
+    };
+    program_state.goto_path(&path).err().map(|failed| {
+        format!(
+            "mark {name}: no {} in {}",
+            path[failed],
+            format_path(&path[..failed])
+        )
+    })
+}
+
+/// Carries out a `:` command, returning the message to show for it. A
+/// command starting with `.` or `[` is a jq path to jump to.
+fn run_command(program_state: &mut ProgramState, command: &str) -> Option<String> {
+    let command = command.trim();
+    if command.starts_with(['.', '[']) {
+        return match path::parse_path(command) {
+            Ok(path) => program_state
+                .goto_path(&path)
+                .err()
+                .map(|failed| format!("no {} in {}", path[failed], format_path(&path[..failed]))),
+            Err(e) => Some(format!("{e:#}")),
+        };
+    }
+
+    let (name, argument) = command.split_once(' ').unwrap_or((command, ""));
+    let argument = argument.trim();
+    match name {
+        "" => None,
+        "stats" => Some(program_state.stats().to_string()),
+        "w" | "write" | "w!" | "write!" if argument.is_empty() => {
+            Some("usage: :write[!] <file>".to_owned())
+        }
+        "w" | "write" | "w!" | "write!" => {
+            let overwrite = name.ends_with('!');
+            let (value, indent) = (program_state.current_subtree(), program_state.indent());
+            Some(match write_json(value, indent, argument, overwrite) {
+                Ok(bytes) => format!("wrote {bytes} bytes to {argument}"),
+                Err(e) => format!("{e:#}"),
+            })
+        }
+        _ => Some(format!("unknown command: {name}")),
+    }
+}
+
+/// Pretty-prints `value`, indented by `indent`, to the file at `path`,
+/// refusing to replace an existing file unless `overwrite` is set. Returns
+/// the bytes written.
+fn write_json(value: &Value, indent: &str, path: &str, overwrite: bool) -> Result<usize> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true);
+    if overwrite {
+        options.create(true).truncate(true);
+    } else {
+        options.create_new(true);
+    }
+    let mut file = match options.open(path) {
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+            anyhow::bail!("{path} exists, use :write! to overwrite")
+        }
+        file => file.context("File Output")?,
+    };
+    let json = load::pretty_json(value, indent)?;
+    file.write_all(json.as_bytes()).context("File Output")?;
+    Ok(json.len())
+}
+
+/// Draws the panels and dividers, returning the selection position
+/// for the status line. Matches of `pattern` are marked in the middle panel.
+fn render_view(
+    out: &mut impl Write,
+    program_state: &ProgramState,
+    search_matched: bool,
+    pattern: Option<&Pattern>,
+    diff: Option<&DiffTree>,
+    source: Option<&SourceMap>,
+    theme: &Theme,
+) -> Result<String> {
+    let mut position = String::new();
+    let panels = program_state.panel_sides().into_iter();
+    for (side, bounds) in panels.zip(program_state.panel_bounds()) {
+        let height = usize::from(bounds.height);
+        let Some(panel) = program_state.panel_state(side) else {
+            render_blank(out, bounds.column, bounds.top, bounds.width, 0..height)?;
+            continue;
+        };
+        match side {
+            PanelSide::Ancestor(_) => {
+                render_col(out, &panel, height, None, diff, theme)?;
+                render_highlight(out, &panel, theme.selection_bg, None, diff, theme)?;
+            }
+            PanelSide::Middle => {
+                render_col(out, &panel, height, pattern, diff, theme)?;
+                let background = if search_matched {
+                    theme.match_bg
+                } else {
+                    theme.selection_bg
+                };
+                render_highlight(out, &panel, background, pattern, diff, theme)?;
+                let len = panel.len();
+                let index = if panel.is_empty() {
+                    0
+                } else {
+                    panel.index() + 1
+                };
+                position += &format!(" {}", program_state.selected_type_label());
+                if program_state.show_size() {
+                    position += &format!(" {}", format_bytes(program_state.selected_byte_size()));
+                }
+                if let Some(diff) = diff {
+                    let mut path = panel.key_path().to_vec();
+                    path.push(panel.text().to_owned());
+                    position += match diff.change_at(&path) {
+                        Change::Same => "",
+                        Change::Added => " added",
+                        Change::Removed => " removed",
+                        Change::Changed => " changed",
+                    };
+                    if let Some(old) = diff.old_at(&path) {
+                        let old = theme.scalar_text(old);
+                        position += &format!(" was {}", fit_string(&old, old.width().min(24)));
+                    }
+                }
+                if let Some(source) = source {
+                    let mut path = panel.key_path().to_vec();
+                    path.push(panel.text().to_owned());
+                    if let Some((line, column)) = source.position_at(&path) {
+                        position += &format!(" at {line}:{column}");
+                    }
+                }
+                if program_state.wrap() {
+                    position += " wrap";
+                }
+                if program_state.reverse_arrays() {
+                    position += " rev";
+                }
+                position += match program_state.key_order() {
+                    KeyOrder::Document => "",
+                    KeyOrder::Ascending => " a-z",
+                    KeyOrder::Descending => " z-a",
+                };
+                position += &format!(" {index}/{len} ");
+            }
+            PanelSide::Right if program_state.preview_values() => {
+                match preview_scalars(&panel, height) {
+                    Some(rows) => render_values(out, &panel, &rows, height, diff, theme)?,
+                    None if panel.lists_entries() => {
+                        render_col(out, &panel, height, None, diff, theme)?;
+                    }
+                    None => {
+                        let depth = program_state.preview_depth();
+                        let decode = program_state.decode_strings();
+                        let indent = program_state.indent();
+                        render_preview(out, &panel, height, depth, decode, indent, theme)?;
+                    }
+                }
+            }
+            PanelSide::Right if panel.lists_entries() => {
+                render_col(out, &panel, height, None, diff, theme)?;
+            }
+            PanelSide::Right => {
+                let depth = program_state.preview_depth();
+                let decode = program_state.decode_strings();
+                let indent = program_state.indent();
+                render_preview(out, &panel, height, depth, decode, indent, theme)?;
+            }
+        }
+    }
+    render_dividers(out, program_state, theme)?;
+    Ok(position)
+}
+
+/// Draws a document that is just one scalar across the whole panel area,
+/// wrapped over as many rows as fit, returning its type for the status line.
+fn render_scalar_root(
+    out: &mut impl Write,
+    program_state: &ProgramState,
+    theme: &Theme,
+) -> Result<String> {
+    let height = program_state.view_height();
+    let width = usize::from(program_state.size().0);
+    let scalar = program_state.root();
+    let rows = scalar_rows(
+        scalar,
+        width.saturating_sub(2),
+        program_state.decode_strings(),
+        theme,
+    );
+    let shown = rows.len().min(height);
+    for (row, text) in rows.into_iter().take(height).enumerate() {
+        let line = u16::try_from(row + 1)?;
         queue!(
-            stdout,
-            MoveTo(0, 0),
-            terminal::Clear(terminal::ClearType::All),
-            SetForegroundColor(Color::DarkGreen),
-            Print(program_state.path_string()),
+            out,
+            MoveTo(0, line),
+            SetForegroundColor(theme.value_color(scalar)),
+            Print(fit_string(&format!(" {text}"), width)),
+        )?;
+    }
+    queue!(out, ResetColor)?;
+    render_blank(out, 0, 1, program_state.size().0, shown..height)?;
+    Ok(format!(" {} ", program_state.selected_type_label()))
+}
+
+/// Draws the tree rows in view over the full width of the panel area,
+/// returning the selection position for the status line.
+fn render_tree(
+    out: &mut impl Write,
+    program_state: &ProgramState,
+    tree: &TreeState,
+    theme: &Theme,
+) -> Result<String> {
+    let height = program_state.view_height();
+    let width = usize::from(program_state.size().0);
+    let offset = tree.scroll();
+    for (row, entry) in tree.rows().iter().enumerate().skip(offset).take(height) {
+        let (leaf, summary) = match entry.value {
+            Value::Object(map) => (map.is_empty(), format!("{{{}}}", map.len())),
+            Value::Array(arr) => (arr.is_empty(), format!("[{}]", arr.len())),
+            scalar => (true, theme.scalar_text(scalar)),
+        };
+        let marker = match (leaf, entry.expanded) {
+            (true, _) => "  ",
+            (false, true) => "▾ ",
+            (false, false) => "▸ ",
+        };
+        let label = format!(" {}{marker}{}: ", "  ".repeat(entry.depth), entry.key);
+        let label_width = label.width().min(width);
+        let selected = row == tree.index();
+        let (background, key_color, value_color) = if selected {
+            (theme.selection_bg, theme.selection_fg, theme.selection_fg)
+        } else {
+            (Color::Reset, theme.key, theme.value_color(entry.value))
+        };
+        let line = u16::try_from(row - offset + 1)?;
+        queue!(
+            out,
+            MoveTo(0, line),
+            SetBackgroundColor(background),
+            SetAttribute(if selected {
+                theme.highlight()
+            } else {
+                Attribute::NoReverse
+            }),
+            SetForegroundColor(key_color),
+            Print(fit_string(&label, label_width)),
+            SetForegroundColor(value_color),
+            Print(fit_string(&summary, width - label_width)),
+            SetAttribute(Attribute::Reset),
             ResetColor,
         )?;
-        if let Some(left) = program_state.panel_state(PanelSide::Left) {
-            render_col(stdout, &left)?;
-            render_highlight(stdout, &left)?;
-        }
-        if let Some(middle) = program_state.panel_state(PanelSide::Middle) {
-            render_col(stdout, &middle)?;
-            render_highlight(stdout, &middle)?;
-            let count = match middle.value() {
-                Value::Object(map) => map.len(),
-                Value::Array(arr) => arr.len(),
-                _ => 1
+    }
+    let listed = tree.rows().len().saturating_sub(offset).min(height);
+    render_blank(out, 0, 1, program_state.size().0, listed..height)?;
+    let shown = if tree.rows().is_empty() {
+        0
+    } else {
+        tree.index() + 1
+    };
+    Ok(format!(
+        " {} tree {shown}/{} ",
+        program_state.selected_type_label(),
+        tree.rows().len()
+    ))
+}
+
+/// Draws the flat view's rows in view over the full width of the panel
+/// area, each leaf's path followed by its value, returning the selection
+/// position for the status line.
+fn render_flat(
+    out: &mut impl Write,
+    program_state: &ProgramState,
+    flat: &FlatState,
+    search_matched: bool,
+    theme: &Theme,
+) -> Result<String> {
+    let height = program_state.view_height();
+    let width = usize::from(program_state.size().0);
+    let offset = flat.scroll();
+    let shown = &flat.rows()[offset..(offset + height).min(flat.rows().len())];
+    // Paths line up in a column of their own, up to half the width.
+    let path_width = shown
+        .iter()
+        .map(|row| row.text.width() + 2)
+        .max()
+        .unwrap_or(0)
+        .min(width / 2);
+    for (row, entry) in shown.iter().enumerate() {
+        let selected = offset + row == flat.index();
+        let (background, key_color, value_color) = if !selected {
+            (Color::Reset, theme.key, theme.value_color(entry.value))
+        } else if search_matched {
+            (theme.match_bg, theme.selection_fg, theme.selection_fg)
+        } else {
+            (theme.selection_bg, theme.selection_fg, theme.selection_fg)
+        };
+        let line = u16::try_from(row + 1)?;
+        queue!(
+            out,
+            MoveTo(0, line),
+            SetBackgroundColor(background),
+            SetAttribute(if selected {
+                theme.highlight()
+            } else {
+                Attribute::NoReverse
+            }),
+            SetForegroundColor(key_color),
+            Print(fit_string(&format!(" {}", entry.text), path_width)),
+            SetForegroundColor(value_color),
+            Print(fit_string(
+                &format!(" {}", theme.scalar_text(entry.value)),
+                width - path_width
+            )),
+            SetAttribute(Attribute::Reset),
+            ResetColor,
+        )?;
+    }
+    render_blank(out, 0, 1, program_state.size().0, shown.len()..height)?;
+    let position = if flat.rows().is_empty() {
+        0
+    } else {
+        flat.index() + 1
+    };
+    Ok(format!(
+        " {} flat {position}/{} ",
+        program_state.selected_type_label(),
+        flat.rows().len()
+    ))
+}
+
+/// Draws the middle panel's array of objects as a table over the full width
+/// of the panel area, a row per element under a row of `columns` names,
+/// returning the selection position for the status line.
+fn render_table(
+    out: &mut impl Write,
+    program_state: &ProgramState,
+    columns: &[String],
+    theme: &Theme,
+) -> Result<String> {
+    let height = program_state.view_height();
+    let width = usize::from(program_state.size().0);
+    let Some(panel) = program_state.panel_state(PanelSide::Middle) else {
+        return Ok(String::new());
+    };
+    let offset = table_offset(&panel, height);
+    let rows = panel.rows(offset, height.saturating_sub(1));
+    let cells: Vec<Vec<String>> = rows
+        .iter()
+        .map(|(_, element)| {
+            columns
+                .iter()
+                .map(|column| table_cell(element.and_then(|e| e.get(column)), theme))
+                .collect()
+        })
+        .collect();
+
+    // Each column is as wide as what it shows, until they no longer all fit
+    // and share out what there is.
+    let key_width = rows.iter().map(|(key, _)| key.width()).max().unwrap_or(0) + 2;
+    let mut widths: Vec<usize> = columns
+        .iter()
+        .enumerate()
+        .map(|(i, column)| {
+            let widest = cells.iter().map(|row| row[i].width()).max().unwrap_or(0);
+            widest.max(column.width()) + 2
+        })
+        .collect();
+    let room = width.saturating_sub(key_width);
+    if widths.iter().sum::<usize>() + widths.len() > room {
+        let share = (room / widths.len()).saturating_sub(1);
+        for column_width in &mut widths {
+            *column_width = (*column_width).min(share);
+        }
+    }
+
+    let mut header = " ".repeat(key_width.min(width));
+    for (column, column_width) in columns.iter().zip(&widths) {
+        header += &format!("│{}", pad_cell(column, *column_width));
+    }
+    queue!(
+        out,
+        MoveTo(0, 1),
+        SetForegroundColor(theme.key),
+        SetAttribute(Attribute::Bold),
+        Print(fit_string(&header, width)),
+        SetAttribute(Attribute::Reset),
+        ResetColor,
+    )?;
+
+    for (row, ((key, element), cells)) in rows.iter().zip(&cells).enumerate() {
+        let selected = offset + row == panel.index();
+        let line = u16::try_from(row + 2)?;
+        queue!(
+            out,
+            MoveTo(0, line),
+            SetBackgroundColor(if selected {
+                theme.selection_bg
+            } else {
+                Color::Reset
+            }),
+            SetAttribute(if selected {
+                theme.highlight()
+            } else {
+                Attribute::NoReverse
+            }),
+            SetForegroundColor(if selected {
+                theme.selection_fg
+            } else {
+                theme.key
+            }),
+            Print(pad_cell(key, key_width.min(width))),
+        )?;
+        let mut used = key_width.min(width);
+        for ((column, cell), column_width) in columns.iter().zip(cells).zip(&widths) {
+            let value = element.and_then(|e| e.get(column));
+            let color = match value {
+                _ if selected => theme.selection_fg,
+                Some(value) => theme.value_color(value),
+                None => theme.null,
             };
-            let (x, y) = program_state.size();
-            let msg = format!("{}/{}", middle.index() + 1, count);
+            let text = format!("│{}", pad_cell(cell, *column_width));
+            let text_width = text.width().min(width - used);
             queue!(
-                stdout,
-                cursor::MoveTo(x - u16::try_from(msg.len()).unwrap(), y),
-                Print(msg)
+                out,
+                SetForegroundColor(color),
+                Print(fit_string(&text, text_width)),
             )?;
+            used += text_width;
+        }
+        queue!(
+            out,
+            Print(" ".repeat(width - used)),
+            SetAttribute(Attribute::Reset),
+            ResetColor,
+        )?;
+    }
+    let listed = rows.len() + 1;
+    render_blank(
+        out,
+        0,
+        1,
+        program_state.size().0,
+        listed.min(height)..height,
+    )?;
+    Ok(format!(
+        " {} table {}/{} ",
+        program_state.selected_type_label(),
+        panel.index() + 1,
+        panel.len()
+    ))
+}
+
+/// The first table row in view: the middle panel's own, unless that leaves
+/// the selection below the `height` rows under the column names.
+fn table_offset(panel: &PanelState, height: usize) -> usize {
+    let offset = panel.scroll_offset();
+    offset.max((panel.index() + 1).saturating_sub(height.saturating_sub(1)))
+}
+
+/// A table cell for `value`: strings without their quotes, other scalars as
+/// JSON, and containers by their entry count.
+fn table_cell(value: Option<&Value>, theme: &Theme) -> String {
+    match value {
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Object(map)) => format!("{{{}}}", map.len()),
+        Some(Value::Array(arr)) => format!("[{}]", arr.len()),
+        Some(scalar) => theme.scalar_text(scalar),
+        None => String::new(),
+    }
+}
+
+/// Fits `text` into `width` columns with a space either side, or blanks
+/// them when that leaves no room for it.
+fn pad_cell(text: &str, width: usize) -> String {
+    match width.checked_sub(2).filter(|w| *w > 0) {
+        Some(inner) => format!(" {} ", fit_string(text, inner)),
+        None => " ".repeat(width),
+    }
+}
+
+/// Lists fuzzy find results over the full width of the panel area,
+/// returning the result count for the status line.
+fn render_finder(
+    out: &mut impl Write,
+    program_state: &ProgramState,
+    finder: &Finder,
+    theme: &Theme,
+) -> Result<String> {
+    let height = program_state.view_height();
+    let width = usize::from(program_state.size().0);
+    let offset = (finder.selected + 1).saturating_sub(height);
+    for (row, path) in finder.results.iter().enumerate().skip(offset).take(height) {
+        let background = if row == finder.selected {
+            theme.selection_bg
+        } else {
+            Color::Reset
+        };
+        let line = u16::try_from(row - offset + 1)?;
+        queue!(
+            out,
+            MoveTo(0, line),
+            SetBackgroundColor(background),
+            SetForegroundColor(theme.key),
+            SetAttribute(if row == finder.selected {
+                theme.highlight()
+            } else {
+                Attribute::NoReverse
+            }),
+            Print(pad_string(&format_path(path), width + 2)),
+            SetAttribute(Attribute::Reset),
+            ResetColor,
+        )?;
+    }
+    let listed = finder.results.len().saturating_sub(offset).min(height);
+    render_blank(out, 0, 1, program_state.size().0, listed..height)?;
+    let shown = if finder.results.is_empty() {
+        0
+    } else {
+        finder.selected + 1
+    };
+    Ok(format!(" {shown}/{} ", finder.results.len()))
+}
+
+/// Draws the entries scrolled into view, labelled with their own keys and
+/// indices. Only those rows are visited, so the cost of a frame follows the
+/// panel height rather than the size of the container.
+fn render_col(
+    out: &mut impl Write,
+    panel_state: &PanelState,
+    height: usize,
+    pattern: Option<&Pattern>,
+    diff: Option<&DiffTree>,
+    theme: &Theme,
+) -> Result<()> {
+    let column = panel_state.column();
+    let top = panel_state.top();
+    let width = usize::from(panel_state.width());
+    let offset = panel_state.scroll_offset();
+
+    let mut drawn = 0;
+    out.queue(cursor::MoveTo(column, top))?;
+    match panel_state.value() {
+        Value::Array(_) | Value::Object(_) => {
+            for (k, v) in panel_state.rows(offset, height) {
+                let (mut color, cell) = match v {
+                    Some(v) => (theme.value_color(v), entry_cell(&k, v, width)),
+                    None => (theme.array, pad_string(&k, width)),
+                };
+                let change = entry_change(diff, panel_state, &k);
+                color = match change {
+                    Change::Same => color,
+                    Change::Added => theme.added,
+                    Change::Removed => theme.removed,
+                    Change::Changed => theme.changed,
+                };
+                let cell = mark_change(cell, change);
+                out.queue(SetForegroundColor(color))?;
+                render_cell(out, &fill(cell, width), &k, pattern, color, theme)?;
+                queue!(out, MoveToNextLine(1), MoveToColumn(column))?;
+                drawn += 1;
+            }
         }
-        if let Some(right) = program_state.panel_state(PanelSide::Right) {
-            render_col(stdout, &right)?;
-            render_highlight(stdout, &right)?;
+        // Printed as JSON, so the string "null" keeps its quotes and can't
+        // be mistaken for null.
+        scalar if height > 0 => {
+            queue!(
+                out,
+                SetForegroundColor(theme.value_color(scalar)),
+                Print(fill(pad_string(&theme.scalar_text(scalar), width), width))
+            )?;
+            drawn = 1;
         }
+        _ => {}
+    }
+    queue!(out, ResetColor)?;
+    render_blank(out, column, top, panel_state.width(), drawn..height)?;
+    render_scrollbar(out, panel_state, height, theme)?;
+    Ok(())
+}
 
-        stdout.flush()?;
+/// The first `height` entries of the array a preview shows, by index, if
+/// they are all scalars. Only those are looked at, so a long array whose
+/// later entries hold containers still lists the ones in view.
+fn preview_scalars<'p>(
+    panel_state: &'p PanelState<'p>,
+    height: usize,
+) -> Option<Vec<(String, &'p Value)>> {
+    let rows: Vec<(String, &Value)> = if panel_state.lists_entries() {
+        panel_state
+            .rows(0, height)
+            .into_iter()
+            .map(|(key, value)| Some((key, value?)))
+            .collect::<Option<_>>()?
+    } else {
+        let Value::Array(arr) = panel_state.value() else {
+            return None;
+        };
+        arr.iter()
+            .take(height)
+            .enumerate()
+            .map(|(index, value)| (index.to_string(), value))
+            .collect()
+    };
+    let scalars = rows
+        .iter()
+        .all(|(_, value)| !matches!(value, Value::Object(_) | Value::Array(_)));
+    (scalars && !rows.is_empty()).then_some(rows)
+}
 
-        let event = read()?;
-        if let Event::Resize(x, y) = event {
-            let (_, new_size) = flush_resize_events((x, y));
-            program_state.resize(new_size);
+/// Draws the scalar entries of a previewed array as one value a row, in
+/// place of their indices.
+fn render_values(
+    out: &mut impl Write,
+    panel_state: &PanelState,
+    rows: &[(String, &Value)],
+    height: usize,
+    diff: Option<&DiffTree>,
+    theme: &Theme,
+) -> Result<()> {
+    let column = panel_state.column();
+    let width = usize::from(panel_state.width());
+    for (row, (key, value)) in rows.iter().enumerate() {
+        let text = theme.scalar_text(value);
+        let change = entry_change(diff, panel_state, key);
+        let color = match change {
+            Change::Same => theme.value_color(value),
+            Change::Added => theme.added,
+            Change::Removed => theme.removed,
+            Change::Changed => theme.changed,
+        };
+        let cell = mark_change(pad_string(&text, width), change);
+        let line = u16::try_from(row + usize::from(panel_state.top()))?;
+        queue!(out, MoveTo(column, line), SetForegroundColor(color))?;
+        render_cell(out, &fill(cell, width), &text, None, color, theme)?;
+    }
+    queue!(out, ResetColor)?;
+    render_blank(
+        out,
+        column,
+        panel_state.top(),
+        panel_state.width(),
+        rows.len()..height,
+    )?;
+    render_scrollbar(out, panel_state, height, theme)?;
+    Ok(())
+}
+
+/// Draws a scrollbar down the last column of a panel listing more entries
+/// than fit in its `height`, the thumb marking which of them are in view.
+/// Entries are padded short of that column, so it is free to draw in.
+fn render_scrollbar(
+    out: &mut impl Write,
+    panel_state: &PanelState,
+    height: usize,
+    theme: &Theme,
+) -> Result<()> {
+    let len = panel_state.len();
+    if len <= height || height == 0 || panel_state.width() == 0 {
+        return Ok(());
+    }
+    let column = panel_state.column() + panel_state.width() - 1;
+    let thumb = (height * height / len).max(1);
+    // Reaches the bottom exactly when the last entry is in view.
+    let start = panel_state.scroll_offset().min(len - height) * (height - thumb) / (len - height);
+    for row in 0..height {
+        let (color, symbol) = if (start..start + thumb).contains(&row) {
+            (theme.scrollbar, '┃')
+        } else {
+            (theme.divider, '┊')
+        };
+        let line = u16::try_from(row + usize::from(panel_state.top()))?;
+        queue!(
+            out,
+            MoveTo(column, line),
+            SetForegroundColor(color),
+            Print(symbol)
+        )?;
+    }
+    out.queue(ResetColor)?;
+    Ok(())
+}
+
+/// Overwrites the given panel rows, counted from screen row `top`, with
+/// spaces, for parts of the frame that have nothing to show.
+fn render_blank(
+    out: &mut impl Write,
+    column: u16,
+    top: u16,
+    width: u16,
+    rows: std::ops::Range<usize>,
+) -> Result<()> {
+    let blank = " ".repeat(width.into());
+    for row in rows {
+        let line = u16::try_from(row + usize::from(top))?;
+        queue!(out, MoveTo(column, line), Print(&blank))?;
+    }
+    Ok(())
+}
+
+/// Pads `text` with spaces to `width` columns.
+fn fill(text: String, width: usize) -> String {
+    let gap = width.saturating_sub(text.width());
+    text + &" ".repeat(gap)
+}
+
+/// A row of the right-panel preview as colored text spans.
+type PreviewLine = Vec<(String, Color)>;
+
+/// Keys the `?` overlay lists after every action, for what isn't bound
+/// through the key map.
+const OTHER_BINDINGS: &[(&str, &str)] = &[
+    ("N<motion>", "repeat a motion N times"),
+    ("Ctrl-c", "quit from anywhere"),
+    ("Alt-c", "in a query, cycle smart / ignore / match case"),
+    ("Alt-r", "in a search or filter, toggle regex"),
+];
+
+/// Draws a syntax-colored, pretty-printed dump of the panel's value, cut off
+/// with `…` once it runs past `height` rows and summarizing containers
+/// nested `depth` levels in. A scalar is wrapped across as many rows as it
+/// needs instead.
+fn render_preview(
+    out: &mut impl Write,
+    panel_state: &PanelState,
+    height: usize,
+    depth: usize,
+    decode: bool,
+    indent: &str,
+    theme: &Theme,
+) -> Result<()> {
+    let column = panel_state.column();
+    let top = usize::from(panel_state.top());
+    let width = usize::from(panel_state.width()).saturating_sub(2);
+
+    let mut preview = Preview {
+        lines: Vec::new(),
+        max_rows: height + 1,
+        decode,
+        indent: &shown_indent(indent),
+        theme,
+    };
+    match panel_state.value() {
+        value @ (Value::Object(_) | Value::Array(_)) => preview.push_value(
+            " ",
+            vec![(" ".to_owned(), theme.punctuation)],
+            value,
+            depth,
+            false,
+        ),
+        scalar => {
+            // Less the space leading each row.
+            let rows = scalar_rows(scalar, width.saturating_sub(1), decode, theme);
+            for row in rows.into_iter().take(height + 1) {
+                preview.lines.push(vec![
+                    (" ".to_owned(), theme.punctuation),
+                    (row, theme.value_color(scalar)),
+                ]);
+            }
         }
+    }
+    let mut lines = preview.lines;
+    if lines.len() > height {
+        lines.truncate(height.saturating_sub(1));
+        lines.push(vec![(" …".to_owned(), theme.punctuation)]);
+    }
+
+    for (row, line) in lines.iter().enumerate() {
+        out.queue(cursor::MoveTo(column, u16::try_from(row + top)?))?;
+        render_line(out, line, width)?;
+        queue!(out, Print("  "))?;
+    }
+    queue!(out, ResetColor)?;
+    render_blank(
+        out,
+        column,
+        panel_state.top(),
+        panel_state.width(),
+        lines.len()..height,
+    )?;
+    Ok(())
+}
 
-        if event == Event::Key(KeyCode::Char('q').into()) {
+/// Prints the colored spans of `line` cut off at `width` columns, padding
+/// with spaces up to it.
+fn render_line(out: &mut impl Write, line: &PreviewLine, width: usize) -> Result<()> {
+    let mut remaining = width;
+    for (text, color) in line {
+        if remaining == 0 {
             break;
         }
-        if event == Event::Key(KeyCode::Char('j').into()) {
-            program_state.inc_index();
+        let text = if text.width() > remaining {
+            fit_string(text, remaining)
+        } else {
+            text.clone()
+        };
+        remaining -= text.width();
+        queue!(out, SetForegroundColor(*color), Print(text))?;
+    }
+    queue!(out, Print(" ".repeat(remaining)))?;
+    Ok(())
+}
+
+/// Draws the lines of the open viewer in view across the panel area,
+/// returning the scroll position for the status line.
+fn render_viewer(
+    out: &mut impl Write,
+    program_state: &ProgramState,
+    viewer: &Viewer,
+) -> Result<String> {
+    let height = program_state.view_height();
+    let width = usize::from(program_state.size().0).saturating_sub(1);
+    let lines = viewer.lines.iter().skip(viewer.scroll).take(height);
+    for (row, line) in lines.enumerate() {
+        let line_row = u16::try_from(row + 1)?;
+        queue!(out, MoveTo(0, line_row), Print(" "))?;
+        render_line(out, line, width)?;
+    }
+    queue!(out, ResetColor)?;
+    let shown = viewer.lines.len().saturating_sub(viewer.scroll).min(height);
+    render_blank(out, 0, 1, program_state.size().0, shown..height)?;
+    Ok(format!(
+        " line {}/{} ",
+        viewer.scroll + 1,
+        viewer.lines.len()
+    ))
+}
+
+/// `indent` as the screen shows it, a tab as four spaces as in strings.
+fn shown_indent(indent: &str) -> String {
+    indent.replace('\t', "    ")
+}
+
+/// Rows showing `scalar` wrapped to `width` columns: as JSON, or with
+/// `decode` a non-empty string as its text with control characters shown
+/// as symbols.
+fn scalar_rows(scalar: &Value, width: usize, decode: bool, theme: &Theme) -> Vec<String> {
+    match scalar {
+        Value::String(text) if decode && !text.is_empty() => {
+            wrap_text(&text.replace('\t', "    "), width)
+                .into_iter()
+                .map(|row| row.chars().map(control_picture).collect())
+                .collect()
         }
-        if event == Event::Key(KeyCode::Char('k').into()) {
-            program_state.dec_index();
+        scalar => wrap_text(&theme.scalar_text(scalar), width),
+    }
+}
+
+/// Breaks `text` into rows at most `width` columns wide, between words
+/// where possible and on grapheme boundaries otherwise. Each line break in
+/// `text` starts a new row.
+fn wrap_text(text: &str, width: usize) -> Vec<String> {
+    let width = width.max(1);
+    let mut rows = Vec::new();
+    for line in text.lines() {
+        let mut row = String::new();
+        let mut used = 0;
+        for word in line.split_word_bounds() {
+            if used > 0 && used + word.width() > width {
+                rows.push(row.trim_end().to_owned());
+                row.clear();
+                used = 0;
+                // The space the row broke at isn't carried over.
+                if word.trim().is_empty() {
+                    continue;
+                }
+            }
+            for grapheme in word.graphemes(true) {
+                let grapheme_width = grapheme.width();
+                if used > 0 && used + grapheme_width > width {
+                    rows.push(std::mem::take(&mut row));
+                    used = 0;
+                }
+                row.push_str(grapheme);
+                used += grapheme_width;
+            }
         }
-        if event == Event::Key(KeyCode::Char('l').into()) {
-            program_state.push_path();
+        rows.push(row);
+    }
+    rows
+}
+
+/// Pretty-printed rows of the right-panel preview, built up to a limit.
+struct Preview<'t> {
+    lines: Vec<PreviewLine>,
+    max_rows: usize,
+    /// Quotes strings with control characters shown as symbols, instead of
+    /// as JSON escapes.
+    decode: bool,
+    /// One level of nesting.
+    indent: &'t str,
+    theme: &'t Theme,
+}
+
+impl Preview<'_> {
+    /// Appends the pretty-printed lines for `value`, starting the first one
+    /// with `prefix` and nesting children under `indent`. Containers deeper
+    /// than `depth` collapse into a `{…}`/`[…]` summary, and nothing is added
+    /// past `max_rows` lines.
+    fn push_value(
+        &mut self,
+        indent: &str,
+        mut prefix: PreviewLine,
+        value: &Value,
+        depth: usize,
+        comma: bool,
+    ) {
+        if self.lines.len() >= self.max_rows {
+            return;
         }
-        if event == Event::Key(KeyCode::Char('h').into()) {
-            program_state.pop_path();
+        let theme = self.theme;
+        let comma = if comma { "," } else { "" };
+        let (open, close, len) = match value {
+            Value::Object(map) => ("{", "}", map.len()),
+            Value::Array(arr) => ("[", "]", arr.len()),
+            Value::String(text) if self.decode => {
+                let text = format!(
+                    "\"{}\"",
+                    text.chars().map(control_picture).collect::<String>()
+                );
+                prefix.push((text, theme.string));
+                prefix.push((comma.to_owned(), theme.punctuation));
+                self.lines.push(prefix);
+                return;
+            }
+            scalar => {
+                prefix.push((theme.scalar_text(scalar), theme.value_color(scalar)));
+                prefix.push((comma.to_owned(), theme.punctuation));
+                self.lines.push(prefix);
+                return;
+            }
+        };
+        if len == 0 || depth == 0 {
+            let summary = if len == 0 {
+                format!("{open}{close}")
+            } else {
+                format!("{open}…{len}{close}")
+            };
+            prefix.push((summary, theme.value_color(value)));
+            prefix.push((comma.to_owned(), theme.punctuation));
+            self.lines.push(prefix);
+            return;
         }
+
+        prefix.push((open.to_owned(), theme.punctuation));
+        self.lines.push(prefix);
+        let child_indent = indent.to_owned() + self.indent;
+        match value {
+            Value::Object(map) => {
+                for (i, (k, v)) in map.iter().enumerate() {
+                    let prefix = vec![
+                        (child_indent.clone(), theme.punctuation),
+                        (Value::from(k.as_str()).to_string(), theme.key),
+                        (": ".to_owned(), theme.punctuation),
+                    ];
+                    self.push_value(&child_indent, prefix, v, depth - 1, i + 1 < len);
+                    if self.lines.len() >= self.max_rows {
+                        return;
+                    }
+                }
+            }
+            Value::Array(arr) => {
+                for (i, v) in arr.iter().enumerate() {
+                    let prefix = vec![(child_indent.clone(), theme.punctuation)];
+                    self.push_value(&child_indent, prefix, v, depth - 1, i + 1 < len);
+                    if self.lines.len() >= self.max_rows {
+                        return;
+                    }
+                }
+            }
+            _ => unreachable!(),
+        }
+        self.lines
+            .push(vec![(format!("{indent}{close}{comma}"), theme.punctuation)]);
     }
+}
 
-    execute!(
-        stdout,
-        cursor::Show,
-        ResetColor,
-        terminal::LeaveAlternateScreen
+/// The visible symbol for a control character, such as `␤` for a line
+/// break, or `c` itself for anything else.
+fn control_picture(c: char) -> char {
+    match c {
+        '\n' => '␤',
+        '\t' => '⇥',
+        '\u{7f}' => '␡',
+        c if c < ' ' => char::from_u32(0x2400 + u32::from(c)).unwrap_or(' '),
+        c => c,
+    }
+}
+
+/// Redraws the selected entry over its row, unless it is out of view.
+fn render_highlight(
+    out: &mut impl Write,
+    panel_state: &PanelState,
+    background: Color,
+    pattern: Option<&Pattern>,
+    diff: Option<&DiffTree>,
+    theme: &Theme,
+) -> Result<()> {
+    let Some(row) = panel_state.row() else {
+        return Ok(());
+    };
+    if panel_state.is_empty() {
+        return Ok(());
+    }
+    queue!(
+        out,
+        cursor::MoveTo(panel_state.column(), row + panel_state.top()),
+        SetBackgroundColor(background),
+        SetForegroundColor(theme.selection_fg),
+        SetAttribute(theme.highlight()),
     )?;
-    terminal::disable_raw_mode()?;
+    let width = panel_state.width().into();
+    match panel_state.selected() {
+        Some(child) => {
+            let cell = entry_cell(panel_state.text(), child, width);
+            let cell = mark_change(cell, entry_change(diff, panel_state, panel_state.text()));
+            render_cell(
+                out,
+                &cell,
+                panel_state.text(),
+                pattern,
+                theme.selection_fg,
+                theme,
+            )?;
+        }
+        // A group of a folded array, named by its range.
+        None if !panel_state.is_empty() && panel_state.lists_entries() => {
+            let cell = pad_string(panel_state.text(), width);
+            render_cell(
+                out,
+                &cell,
+                panel_state.text(),
+                pattern,
+                theme.selection_fg,
+                theme,
+            )?;
+        }
+        None => {
+            let text = theme.scalar_text(panel_state.value());
+            out.queue(Print(pad_string(&text, width)))?;
+        }
+    }
+    queue!(out, SetAttribute(Attribute::Reset), ResetColor)?;
     Ok(())
 }
 
-fn render_col(stdout: &mut io::Stdout, panel_state: &PanelState) -> Result<()> {
-    let column = panel_state.column();
-    let width = panel_state.width();
+/// Prints a panel `cell` showing `key` in `color`, with the first match of
+/// `pattern` in it bold and underlined. A `…` cutting the key short is set
+/// apart in the theme's `truncated` color, so it can't pass for the key.
+fn render_cell(
+    out: &mut impl Write,
+    cell: &str,
+    key: &str,
+    pattern: Option<&Pattern>,
+    color: Color,
+    theme: &Theme,
+) -> Result<()> {
+    let shown = shown_chars(cell, key);
+    let cut = cell
+        .char_indices()
+        .nth(1 + shown)
+        .filter(|(_, c)| *c == '…' && shown < key.chars().count());
+    if let Some((at, _)) = cut {
+        render_cell(out, &cell[..at], key, pattern, color, theme)?;
+        queue!(
+            out,
+            SetForegroundColor(theme.truncated),
+            Print('…'),
+            SetForegroundColor(color),
+            Print(&cell[at + '…'.len_utf8()..]),
+        )?;
+        return Ok(());
+    }
+    let Some((before, matched, after)) = pattern
+        .and_then(|pattern| pattern.find(key))
+        .and_then(|range| split_match(cell, key, range))
+    else {
+        out.queue(Print(cell))?;
+        return Ok(());
+    };
+    queue!(
+        out,
+        Print(before),
+        SetAttribute(Attribute::Bold),
+        SetAttribute(Attribute::Underlined),
+        Print(matched),
+        SetAttribute(Attribute::NormalIntensity),
+        SetAttribute(Attribute::NoUnderline),
+        Print(after),
+    )?;
+    Ok(())
+}
 
-    stdout.queue(cursor::MoveTo(column, 1))?;
-    match panel_state.value() {
-        Value::Array(vec) => {
-            for i in 0..vec.len() {
-                queue!(
-                    stdout,
-                    Print(pad_string(&i.to_string(), width.into())),
-                    MoveToNextLine(1),
-                    MoveToColumn(column)
-                )?;
+/// How many characters of `key` `cell` shows from its second column. Keys
+/// are shown a character for a character, control characters as spaces,
+/// until they run out or are cut short with `…`.
+fn shown_chars(cell: &str, key: &str) -> usize {
+    cell.chars()
+        .skip(1)
+        .zip(key.chars())
+        .take_while(|(shown, c)| shown == c || c.is_control())
+        .count()
+}
+
+/// Splits `cell`, which shows `key` from its second column, around the
+/// bytes `range` of `key`. Only what is left of the match once the key is
+/// cut short to fit counts, and `None` means none of it is shown.
+fn split_match<'c>(
+    cell: &'c str,
+    key: &str,
+    range: Range<usize>,
+) -> Option<(&'c str, &'c str, &'c str)> {
+    let shown = shown_chars(cell, key);
+    let start = 1 + key[..range.start].chars().count();
+    let end = (1 + key[..range.end].chars().count()).min(1 + shown);
+    if start >= end {
+        return None;
+    }
+    let byte = |chars: usize| {
+        cell.char_indices()
+            .nth(chars)
+            .map_or(cell.len(), |(i, _)| i)
+    };
+    let (start, end) = (byte(start), byte(end));
+    Some((&cell[..start], &cell[start..end], &cell[end..]))
+}
+
+fn render_dividers(
+    out: &mut impl Write,
+    program_state: &ProgramState,
+    theme: &Theme,
+) -> Result<()> {
+    let height = u16::try_from(program_state.view_height())?;
+    let width = usize::from(program_state.size().0);
+    out.queue(SetForegroundColor(theme.divider))?;
+    for divider in program_state.dividers() {
+        match program_state.layout() {
+            Layout::Vertical => {
+                for row in 1..=height {
+                    queue!(out, MoveTo(divider, row), Print('│'))?;
+                }
             }
+            Layout::Horizontal => queue!(out, MoveTo(0, divider), Print("─".repeat(width)))?,
         }
-        Value::Object(map) => {
-            for k in map.keys() {
+    }
+    out.queue(ResetColor)?;
+    Ok(())
+}
+
+/// Draws a bordered box in the middle of the screen listing the keys
+/// `keymap` binds to each of `Action::ALL`, followed by `OTHER_BINDINGS`,
+/// cutting rows that don't fit. Actions left without a key are skipped.
+fn render_help(
+    out: &mut impl Write,
+    program_state: &ProgramState,
+    keymap: &KeyMap,
+    theme: &Theme,
+) -> Result<()> {
+    let (cols, rows) = program_state.size();
+    let bindings: Vec<(String, &str)> = Action::ALL
+        .into_iter()
+        .map(|action| (keymap.keys_label(action), action.description()))
+        .filter(|(keys, _)| !keys.is_empty())
+        .chain(
+            OTHER_BINDINGS
+                .iter()
+                .map(|(keys, description)| ((*keys).to_owned(), *description)),
+        )
+        .collect();
+    let key_width = bindings
+        .iter()
+        .map(|(key, _)| key.width())
+        .max()
+        .unwrap_or(0);
+    let action_width = bindings
+        .iter()
+        .map(|(_, action)| action.width())
+        .max()
+        .unwrap_or(0);
+    let inner = (key_width + action_width + 7).min(usize::from(cols).saturating_sub(2));
+    let shown = bindings.len().min(usize::from(rows).saturating_sub(2));
+    let left = (usize::from(cols) - (inner + 2).min(usize::from(cols))) / 2;
+    let top = (usize::from(rows) - (shown + 2).min(usize::from(rows))) / 2;
+
+    let mut lines = vec![format!("┌{}┐", "─".repeat(inner))];
+    for (key, action) in &bindings[..shown] {
+        let key = format!("{key:<key_width$}");
+        lines.push(format!(
+            "│{}│",
+            fit_string(&format!("  {key}   {action}"), inner)
+        ));
+    }
+    lines.push(format!("└{}┘", "─".repeat(inner)));
+
+    let left = u16::try_from(left)?;
+    for (offset, line) in lines.iter().enumerate() {
+        let row = u16::try_from(top + offset)?;
+        queue!(
+            out,
+            MoveTo(left, row),
+            SetForegroundColor(theme.key),
+            Print(line),
+            ResetColor,
+        )?;
+    }
+    Ok(())
+}
+
+/// Draws the path on the top row, after a strip of the open tabs with the
+/// one in front highlighted if there is more than one, and the container
+/// in focus at the end.
+fn render_header(
+    out: &mut impl Write,
+    program_state: &ProgramState,
+    (labels, active): (&[String], usize),
+    theme: &Theme,
+) -> Result<()> {
+    let cols = usize::from(program_state.size().0);
+    let mut used = 0;
+    out.queue(MoveTo(0, 0))?;
+    if labels.len() > 1 {
+        for (index, label) in labels.iter().enumerate() {
+            let text = format!(" {label} ");
+            let text = fit_string(&text, text.width().min(cols - used));
+            used += text.width();
+            if index == active {
                 queue!(
-                    stdout,
-                    Print(pad_string(k, width.into())),
-                    MoveToNextLine(1),
-                    MoveToColumn(column)
+                    out,
+                    SetBackgroundColor(theme.selection_bg),
+                    SetForegroundColor(theme.selection_fg),
+                    SetAttribute(theme.highlight()),
+                    Print(text),
+                    SetAttribute(Attribute::Reset),
+                    ResetColor,
                 )?;
+            } else {
+                queue!(out, SetForegroundColor(theme.key), Print(text))?;
             }
         }
-        _ => queue!(stdout, Print(pad_string(panel_state.text(), width.into())))?,
+        let divider = fit_string(" │ ", 3.min(cols - used));
+        used += divider.width();
+        queue!(out, SetForegroundColor(theme.divider), Print(divider))?;
     }
+    // The container in focus is named at the right end, if it fits.
+    let focus = format!(" {} ", program_state.focus_label());
+    let focus_width = if focus.width() + 8 <= cols - used {
+        focus.width()
+    } else {
+        0
+    };
+    queue!(
+        out,
+        SetForegroundColor(theme.header),
+        Print(fit_string(
+            &program_state.path_string(),
+            cols - used - focus_width
+        )),
+        SetForegroundColor(theme.value_color(program_state.current())),
+        Print(fit_string(&focus, focus_width)),
+        ResetColor,
+    )?;
     Ok(())
 }
 
-fn render_highlight(stdout: &mut io::Stdout, panel_state: &PanelState) -> Result<()> {
+/// Draws the path bar on the last row with `info` right-aligned.
+fn render_status(
+    out: &mut impl Write,
+    program_state: &ProgramState,
+    info: &str,
+    theme: &Theme,
+) -> Result<()> {
+    let (cols, rows) = program_state.size();
+    let cols = usize::from(cols);
+    let info_width = info.width().min(cols);
+    let line = fit_string(&program_state.current_path(), cols - info_width)
+        + &fit_string(info, info_width);
     queue!(
-        stdout,
-        cursor::MoveTo(panel_state.column(), panel_state.index() + 1),
-        SetBackgroundColor(Color::DarkBlue),
-        SetForegroundColor(Color::Black),
-        Print(pad_string(panel_state.text(), panel_state.width().into())),
+        out,
+        cursor::MoveTo(0, rows.saturating_sub(1)),
+        SetBackgroundColor(theme.status_bg),
+        SetForegroundColor(theme.status_fg),
+        SetAttribute(theme.status_highlight()),
+        Print(line),
+        SetAttribute(Attribute::Reset),
         ResetColor,
     )?;
     Ok(())
 }
 
+/// Pads a container entry like `pad_string`, right-aligning a `{n}` or `[n]`
+/// child count when the entry leads into a nested object or array.
+fn entry_cell(key: &str, value: &Value, width: usize) -> String {
+    let marker = match value {
+        Value::Object(map) => format!("{{{}}}", map.len()),
+        Value::Array(arr) => format!("[{}]", arr.len()),
+        _ => return pad_string(key, width),
+    };
+    let key_width = width.saturating_sub(4 + marker.width() + 1);
+    if key_width == 0 {
+        return pad_string(key, width);
+    }
+    format!(" {} {marker} ", fit_string(key, key_width))
+}
+
+/// How the entry `key` of the panel differs between the files of a diff,
+/// or `Same` when there is none.
+fn entry_change(diff: Option<&DiffTree>, panel_state: &PanelState, key: &str) -> Change {
+    diff.map_or(Change::Same, |diff| {
+        let mut path = panel_state.key_path().to_vec();
+        path.push(key.to_owned());
+        diff.change_at(&path)
+    })
+}
+
+/// Marks `cell` with `+`, `-` or `~` in its blank first column for an added,
+/// removed or changed entry, so a diff reads without colors too.
+fn mark_change(cell: String, change: Change) -> String {
+    let mark = match change {
+        Change::Same => return cell,
+        Change::Added => '+',
+        Change::Removed => '-',
+        Change::Changed => '~',
+    };
+    match cell.strip_prefix(' ') {
+        Some(rest) => format!("{mark}{rest}"),
+        None => cell,
+    }
+}
+
 fn pad_string(str: &str, width: usize) -> String {
-    let width = width - 4;
-    if str.len() > width {
-        let mut str = str.to_owned();
-        str.truncate(width - 1);
-        str += "~";
-        return format!(" {str:width$} ");
+    // Panels too narrow for any text still keep their blank footprint.
+    match width.checked_sub(4).filter(|w| *w > 0) {
+        Some(width) => format!(" {} ", fit_string(str, width)),
+        None => " ".repeat(width.saturating_sub(2)),
+    }
+}
+
+/// `bytes` in the largest unit that leaves at least one of it, such as
+/// `512 B` or `1.5 KiB`.
+fn format_bytes(bytes: usize) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{size:.1} {}", UNITS[unit])
+}
+
+/// Notes on a query prompt how the query is matched, when that isn't as
+/// smart-case text, and why it can't be if it is a bad regex. The fuzzy
+/// finder, with no `query` given, is never a regex.
+fn query_note(program_state: &ProgramState, query: Option<&str>) -> String {
+    let mut notes = Vec::new();
+    if let (true, Some(query)) = (program_state.regex(), query) {
+        notes.push(match program_state.query_error(query) {
+            Some(error) => {
+                // Syntax errors quote the pattern over several lines before
+                // saying what is wrong with it.
+                let error = error.to_string();
+                let reason = error.lines().last().unwrap_or_default();
+                format!("regex: {}", reason.trim_start_matches("error: "))
+            }
+            None => "regex".to_owned(),
+        });
+    }
+    match program_state.case() {
+        Case::Smart => {}
+        Case::Insensitive => notes.push("ignore case".to_owned()),
+        Case::Sensitive => notes.push("match case".to_owned()),
+    }
+    if notes.is_empty() {
+        String::new()
+    } else {
+        format!("  ({})", notes.join(", "))
+    }
+}
+
+/// Fits `str` into exactly `width` display columns, truncating with `…` on a
+/// grapheme boundary when it is too long. Control characters are shown as
+/// spaces so a value can never break onto another row.
+fn fit_string(str: &str, width: usize) -> String {
+    if width == 0 {
+        return String::new();
     }
-    format!(" {str:width$} ")
+    let str: String = str
+        .chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect();
+    if str.width() <= width {
+        let fill = width - str.width();
+        return str + &" ".repeat(fill);
+    }
+
+    let mut text = String::new();
+    let mut used = 0;
+    for grapheme in str.graphemes(true) {
+        let grapheme_width = grapheme.width();
+        if used + grapheme_width > width - 1 {
+            break;
+        }
+        text.push_str(grapheme);
+        used += grapheme_width;
+    }
+    text + "…" + &" ".repeat(width - 1 - used)
 }
 
 fn flush_resize_events(first_resize: (u16, u16)) -> ((u16, u16), (u16, u16)) {
@@ -181,3 +3024,193 @@ fn flush_resize_events(first_resize: (u16, u16)) -> ((u16, u16), (u16, u16)) {
 
     (first_resize, last_resize)
 }
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+    use serde_json::json;
+
+    use super::*;
+
+    /// A `Ui` for a single tab, with the default keys and colors. Those are
+    /// leaked, so that it can borrow them for as long as any test needs.
+    fn ui() -> Ui<'static> {
+        let keymap = Box::leak(Box::new(KeyMap::default()));
+        let theme = Box::leak(Box::new(Theme::default()));
+        let labels = Box::leak(Box::new(["test".to_owned()]));
+        Ui::new(keymap, theme, (labels, 0), None, None)
+    }
+
+    /// Draws a whole frame of `program_state` the way `run` does.
+    fn draw(program_state: &ProgramState) -> Vec<u8> {
+        let mut ui = ui();
+        let mut frame = Vec::new();
+        ui.draw(&mut frame, program_state, None).unwrap();
+        frame
+    }
+
+    #[test]
+    fn pad_string_counts_wide_characters_by_cell() {
+        for key in ["日本語", "🎉🚀✨", "a日b🎉"] {
+            for width in 0..=12 {
+                let cell = pad_string(key, width);
+                assert_eq!(cell.width(), width.saturating_sub(2), "{key} at {width}");
+            }
+        }
+        assert_eq!(pad_string("日本語", 10), " 日本語 ");
+        // A wide character that would straddle the edge gives way to `…`.
+        assert_eq!(pad_string("日本語", 9), " 日本… ");
+        assert_eq!(pad_string("🎉🚀✨", 8), " 🎉…  ");
+    }
+
+    #[test]
+    fn a_scalar_root_draws_across_the_screen() {
+        let value = json!(true);
+        let state = ProgramState::new(&value, (80, 24));
+        let frame = String::from_utf8(draw(&state)).unwrap();
+        assert!(frame.contains("true"));
+    }
+
+    #[test]
+    fn bad_regexes_say_what_is_wrong_on_one_line() {
+        let value = json!({});
+        let mut state = ProgramState::new(&value, (80, 24));
+        state.toggle_regex();
+        assert_eq!(query_note(&state, Some("(a")), "  (regex: unclosed group)");
+        assert_eq!(query_note(&state, Some("(a)")), "  (regex)");
+    }
+
+    fn key(c: char) -> Event {
+        Event::Key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE))
+    }
+
+    #[test]
+    fn keys_become_actions_repeated_by_the_count() {
+        let mut ui = ui();
+        assert!(ui.input(&key('1')).is_none());
+        assert!(ui.input(&key('2')).is_none());
+        let input = ui.input(&key('j'));
+        assert!(matches!(input, Some(Input::Actions(actions, 12)) if actions == [Action::Down]));
+        let input = ui.input(&key('k'));
+        assert!(matches!(input, Some(Input::Actions(actions, 1)) if actions == [Action::Up]));
+        assert!(matches!(
+            ui.input(&Event::Resize(80, 24)),
+            Some(Input::Other)
+        ));
+    }
+
+    /// Hands `event` to `ui` as `run` does, with no clipboard to copy to.
+    fn press<'a>(ui: &mut Ui<'a>, state: &mut ProgramState<'a>, event: Event) -> Next {
+        let mut message = None;
+        ui.handle(&event, state, &mut Clipboard::new(), &mut message)
+            .unwrap()
+    }
+
+    #[test]
+    fn marks_are_gone_back_to_with_a_quote() {
+        let keymap = KeyMap::default();
+        let press = |c| {
+            let key = KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE);
+            keymap.press(&mut Vec::new(), key.into())
+        };
+        assert!(press('m') == [Action::Mark]);
+        assert!(press('\'') == [Action::GotoMark]);
+        assert!(press('`') == [Action::GotoMark]);
+        assert!(press(';') == [Action::Jump]);
+    }
+
+    #[test]
+    fn keys_move_descend_and_ascend() {
+        let value = json!({"a": {"b": 1, "c": 2}, "d": 3});
+        let mut state = ProgramState::new(&value, (80, 24));
+        let mut ui = ui();
+        press(&mut ui, &mut state, key('j'));
+        assert_eq!(state.current_path(), ".d");
+        press(&mut ui, &mut state, key('k'));
+        assert_eq!(state.current_path(), ".a");
+        press(&mut ui, &mut state, key('l'));
+        assert_eq!(
+            (state.depth(), state.current_path()),
+            (1, ".a.b".to_owned())
+        );
+        press(&mut ui, &mut state, key('j'));
+        assert_eq!(state.current_subtree(), &json!(2));
+        press(&mut ui, &mut state, key('h'));
+        assert_eq!((state.depth(), state.current_path()), (0, ".a".to_owned()));
+    }
+
+    #[test]
+    fn q_and_ctrl_c_quit() {
+        let value = json!({"a": 1});
+        let mut state = ProgramState::new(&value, (80, 24));
+        let mut ui = ui();
+        let ctrl_c = Event::Key(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL));
+        for event in [key('q'), ctrl_c] {
+            let next = press(&mut ui, &mut state, event);
+            assert!(matches!(next, Next::Stop(Outcome::Quit)));
+        }
+    }
+
+    #[test]
+    fn resizing_keeps_the_selection_in_view() {
+        let value: Value = (0..100).collect();
+        let mut state = ProgramState::new(&value, (80, 24));
+        let mut ui = ui();
+        state.goto_last();
+        ui.resize(&mut state, (40, 10));
+        let next = press(&mut ui, &mut state, Event::Resize(40, 10));
+        assert!(matches!(next, Next::DrawIfChanged));
+        assert_eq!(state.size(), (40, 10));
+        assert_eq!(state.current_path(), "[99]");
+        let side = state.panel_sides()[0];
+        let row = state.panel_state(side).and_then(|panel| panel.row());
+        assert!(row.is_some_and(|row| usize::from(row) < state.view_height()));
+    }
+
+    #[test]
+    fn printing_the_path_on_exit_keeps_the_colors_of_the_terminal() {
+        // `$(jmv --print-path-on-exit …)` captures stdout, but `screen` draws
+        // to the terminal all the same, and colors go by that.
+        let options = Cli::try_parse_from(["jmv", "--print-path-on-exit", "f.json"]).unwrap();
+        let theme = load_theme(&options, true);
+        assert!(theme.string == Color::Green && !theme.reverse);
+        assert!(load_theme(&options, false).reverse);
+    }
+
+    #[test]
+    fn entering_an_empty_object_draws() {
+        let value = json!({"a": {}});
+        let mut state = ProgramState::new(&value, (120, 40));
+        state.push_path(1);
+        assert_eq!(state.depth(), 1);
+        assert_eq!(state.current(), &json!({}));
+        for side in state.panel_sides() {
+            state.panel_state(side);
+        }
+        assert!(!draw(&state).is_empty());
+        // Nothing to enter further, and back out again.
+        state.push_path(1);
+        assert_eq!(state.depth(), 1);
+        state.pop_path(1);
+        assert_eq!(state.current_path(), ".a");
+        assert!(!draw(&state).is_empty());
+    }
+
+    #[test]
+    fn pad_string_keeps_narrow_panels_blank() {
+        for width in 0..=4 {
+            let cell = pad_string("key", width);
+            assert_eq!(cell.width(), width.saturating_sub(2), "width {width}");
+            assert!(cell.chars().all(|c| c == ' '), "width {width}");
+        }
+    }
+
+    #[test]
+    fn pad_string_truncates_to_fit() {
+        // Two columns go to the panel's edges.
+        assert_eq!(pad_string("abcdef", 5), " … ");
+        assert_eq!(pad_string("abcdef", 7), " ab… ");
+        assert_eq!(pad_string("abc", 7), " abc ");
+        assert_eq!(pad_string("ab", 7), " ab  ");
+    }
+}
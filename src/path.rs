@@ -0,0 +1,144 @@
+use std::fmt;
+
+use anyhow::{bail, Context, Result};
+
+use serde_json::Value;
+
+/// One step from a container to a child, as written in a jq path.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+impl fmt::Display for PathSegment {
+    /// Formats `[n]` for array elements, `.key` for plain object keys and
+    /// `["key"]` for keys that need quoting.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PathSegment::Index(index) => write!(f, "[{index}]"),
+            PathSegment::Key(key) => {
+                let mut chars = key.chars();
+                let is_identifier = chars
+                    .next()
+                    .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+                    && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+                if is_identifier {
+                    write!(f, ".{key}")
+                } else {
+                    write!(f, "[{}]", serde_json::Value::from(key.as_str()))
+                }
+            }
+        }
+    }
+}
+
+/// Follows `path` down from `root`, or returns the position of the first
+/// segment that isn't there.
+pub fn resolve<'v>(root: &'v Value, path: &[PathSegment]) -> Result<&'v Value, usize> {
+    path.iter()
+        .enumerate()
+        .try_fold(root, |node, (position, step)| {
+            match (node, step) {
+                (Value::Object(map), PathSegment::Key(key)) => map.get(key),
+                (Value::Array(arr), PathSegment::Index(index)) => arr.get(*index),
+                _ => None,
+            }
+            .ok_or(position)
+        })
+}
+
+/// Calls `visit` with the path and value of every node below `value`, in
+/// document order and each container before its children. `path` leads to
+/// `value` and is left as it was.
+pub fn walk<'v>(
+    value: &'v Value,
+    path: &mut Vec<PathSegment>,
+    visit: &mut impl FnMut(&[PathSegment], &'v Value),
+) {
+    let children: Box<dyn Iterator<Item = (PathSegment, &Value)>> = match value {
+        Value::Object(map) => Box::new(map.iter().map(|(k, v)| (PathSegment::Key(k.clone()), v))),
+        Value::Array(arr) => Box::new(
+            arr.iter()
+                .enumerate()
+                .map(|(i, v)| (PathSegment::Index(i), v)),
+        ),
+        _ => return,
+    };
+    for (step, child) in children {
+        path.push(step);
+        visit(path, child);
+        walk(child, path, visit);
+        path.pop();
+    }
+}
+
+/// Joins segments into a jq path, with `.` standing for the root.
+pub fn format_path(path: &[PathSegment]) -> String {
+    if path.is_empty() {
+        return ".".to_owned();
+    }
+    path.iter().map(ToString::to_string).collect()
+}
+
+/// Parses a jq path made of `.key`, `["key"]` and `[index]` segments. A
+/// lone `.` is the root.
+pub fn parse_path(text: &str) -> Result<Vec<PathSegment>> {
+    let text = text.trim();
+    let mut segments = Vec::new();
+    let mut rest = text;
+    while !rest.is_empty() {
+        let column = text.len() - rest.len() + 1;
+        if let Some(after) = rest.strip_prefix('.') {
+            if after.is_empty() && segments.is_empty() {
+                break;
+            }
+            if after.starts_with('[') {
+                rest = after;
+                continue;
+            }
+            let end = after.find(['.', '[']).unwrap_or(after.len());
+            if end == 0 {
+                bail!("Expected a key at column {}", column + 1);
+            }
+            segments.push(PathSegment::Key(after[..end].to_owned()));
+            rest = &after[end..];
+        } else if let Some(after) = rest.strip_prefix('[') {
+            let end = closing_bracket(after)
+                .with_context(|| format!("Unclosed `[` at column {column}"))?;
+            let inner = after[..end].trim();
+            let segment = if inner.starts_with('"') {
+                serde_json::from_str(inner)
+                    .map(PathSegment::Key)
+                    .with_context(|| format!("Invalid key at column {column}"))?
+            } else {
+                inner
+                    .parse()
+                    .map(PathSegment::Index)
+                    .with_context(|| format!("Invalid index at column {column}"))?
+            };
+            segments.push(segment);
+            rest = &after[end + 1..];
+        } else {
+            bail!("Expected `.` or `[` at column {column}");
+        }
+    }
+    Ok(segments)
+}
+
+/// Byte offset of the `]` ending a bracketed segment, skipping any inside a
+/// quoted key.
+fn closing_bracket(text: &str) -> Option<usize> {
+    let mut quoted = false;
+    let mut escaped = false;
+    for (offset, c) in text.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            ']' if !quoted => return Some(offset),
+            _ => {}
+        }
+    }
+    None
+}
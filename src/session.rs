@@ -0,0 +1,75 @@
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+
+use serde_json::{Map, Value};
+
+use cjv::path::{format_path, parse_path, PathSegment};
+
+/// Files remembered at most, dropping the least recently closed first.
+const LIMIT: usize = 200;
+
+/// The file holding where each document was left, as an object from
+/// absolute input path to jq path.
+fn state_file() -> Option<PathBuf> {
+    let dir = match env::var_os("XDG_STATE_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(env::var_os("HOME")?).join(".local/state"),
+    };
+    Some(dir.join("jmv").join("sessions.json"))
+}
+
+fn read_sessions(file: &Path) -> Map<String, Value> {
+    fs::read_to_string(file)
+        .ok()
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+fn key(input: &Path) -> Option<String> {
+    let absolute = input.canonicalize().ok()?;
+    Some(absolute.to_string_lossy().into_owned())
+}
+
+/// Where `input` was left last time, if anywhere.
+pub fn load(input: &Path) -> Option<Vec<PathSegment>> {
+    let sessions = read_sessions(&state_file()?);
+    let saved = sessions.get(&key(input)?)?.as_str()?;
+    parse_path(saved).ok()
+}
+
+/// Records `path` as where `input` was left.
+pub fn save(input: &Path, path: &[PathSegment]) -> Result<()> {
+    let (Some(file), Some(key)) = (state_file(), key(input)) else {
+        return Ok(());
+    };
+    let mut sessions = read_sessions(&file);
+    // Re-inserted at the end, so the oldest entries are first to go.
+    sessions.shift_remove(&key);
+    sessions.insert(key, Value::String(format_path(path)));
+    while sessions.len() > LIMIT {
+        let Some(oldest) = sessions.keys().next().cloned() else {
+            break;
+        };
+        sessions.shift_remove(&oldest);
+    }
+    if let Some(dir) = file.parent() {
+        fs::create_dir_all(dir).context("Session State")?;
+    }
+    fs::write(&file, Value::Object(sessions).to_string()).context("Session State")?;
+    Ok(())
+}
+
+/// Forgets every saved position.
+pub fn clear() -> Result<()> {
+    let Some(file) = state_file() else {
+        return Ok(());
+    };
+    match fs::remove_file(file) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e).context("Session State"),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,180 @@
+//! Where each value of a JSON document starts in the text it was parsed
+//! from, for telling where the selection comes from.
+
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use crate::load::Format;
+
+/// The start of every value in a document. Object entries go by their key
+/// and array entries by their index, as in a `DiffTree`.
+pub struct SourceMap {
+    root: Node,
+    /// Byte offset of the start of each line.
+    lines: Vec<usize>,
+}
+
+struct Node {
+    offset: usize,
+    children: HashMap<String, Node>,
+}
+
+/// Finds where the values of `text` start, read as `format` the way
+/// `load::parse` reads it: JSON that turns out to be JSONL has a line for
+/// each entry. `None` for JSON5, YAML and TOML, and for text that doesn't
+/// scan.
+pub fn locate(text: &str, format: Format) -> Option<SourceMap> {
+    let root = match format {
+        Format::Json => {
+            let mut scanner = Scanner { text, at: 0 };
+            let first = scanner.value()?;
+            scanner.skip_space();
+            if scanner.at < text.len() && is_jsonl(text) {
+                lines_of(text)
+            } else {
+                first
+            }
+        }
+        Format::Jsonl => lines_of(text),
+        Format::Json5 | Format::Yaml | Format::Toml => return None,
+    };
+    let lines = std::iter::once(0)
+        .chain(text.match_indices('\n').map(|(at, _)| at + 1))
+        .collect();
+    Some(SourceMap { root, lines })
+}
+
+impl SourceMap {
+    /// The 1-based line and byte column the value at `path` starts at.
+    pub fn position_at(&self, path: &[String]) -> Option<(usize, usize)> {
+        let mut node = &self.root;
+        for key in path {
+            node = node.children.get(key)?;
+        }
+        let line = self.lines.partition_point(|start| *start <= node.offset);
+        Some((line, node.offset - self.lines[line - 1] + 1))
+    }
+}
+
+/// Whether `load::parse` takes JSON `text` for JSONL: more than one line
+/// holds a value, and every line that isn't blank holds exactly one.
+fn is_jsonl(text: &str) -> bool {
+    let mut lines = text.lines().filter(|line| !line.trim().is_empty());
+    lines.clone().count() > 1 && lines.all(|line| serde_json::from_str::<Value>(line).is_ok())
+}
+
+/// The lines of JSONL `text` as the entries of an array. Lines that don't
+/// parse are skipped, as they are when loading.
+fn lines_of(text: &str) -> Node {
+    let mut children = HashMap::new();
+    let mut start = 0;
+    for line in text.split_inclusive('\n') {
+        let valid = !line.trim().is_empty() && serde_json::from_str::<Value>(line).is_ok();
+        let scanned = valid.then(|| Scanner { text, at: start }.value()).flatten();
+        if let Some(node) = scanned {
+            children.insert(children.len().to_string(), node);
+        }
+        start += line.len();
+    }
+    Node {
+        offset: 0,
+        children,
+    }
+}
+
+/// Steps through JSON text, trusting it to be well formed, as it has
+/// already been parsed.
+struct Scanner<'t> {
+    text: &'t str,
+    at: usize,
+}
+
+impl Scanner<'_> {
+    fn value(&mut self) -> Option<Node> {
+        self.skip_space();
+        let offset = self.at;
+        let mut children = HashMap::new();
+        match self.peek()? {
+            b'{' => {
+                self.at += 1;
+                while !self.close(b'}')? {
+                    let key = self.string()?;
+                    self.skip_space();
+                    self.expect(b':')?;
+                    // A repeated key keeps its last value, as when parsing.
+                    children.insert(key, self.value()?);
+                }
+            }
+            b'[' => {
+                self.at += 1;
+                while !self.close(b']')? {
+                    children.insert(children.len().to_string(), self.value()?);
+                }
+            }
+            b'"' => {
+                self.string()?;
+            }
+            _ => {
+                let rest = &self.text.as_bytes()[self.at..];
+                let len = rest
+                    .iter()
+                    .position(|b| matches!(b, b',' | b']' | b'}') || b.is_ascii_whitespace())
+                    .unwrap_or(rest.len());
+                if len == 0 {
+                    return None;
+                }
+                self.at += len;
+            }
+        }
+        Some(Node { offset, children })
+    }
+
+    /// Steps past the comma before the next entry of a container, returning
+    /// whether the container ends with `end` instead.
+    fn close(&mut self, end: u8) -> Option<bool> {
+        self.skip_space();
+        if self.peek()? == b',' {
+            self.at += 1;
+            self.skip_space();
+        }
+        let closed = self.peek()? == end;
+        if closed {
+            self.at += 1;
+        }
+        Some(closed)
+    }
+
+    /// Reads a string, escapes and all, returning its text.
+    fn string(&mut self) -> Option<String> {
+        self.skip_space();
+        let start = self.at;
+        self.expect(b'"')?;
+        let bytes = self.text.as_bytes();
+        loop {
+            match bytes.get(self.at)? {
+                b'\\' => self.at += 2,
+                b'"' => break,
+                _ => self.at += 1,
+            }
+        }
+        self.at += 1;
+        serde_json::from_str(self.text.get(start..self.at)?).ok()
+    }
+
+    fn expect(&mut self, byte: u8) -> Option<()> {
+        (self.peek()? == byte).then(|| self.at += 1)
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.text.as_bytes().get(self.at).copied()
+    }
+
+    fn skip_space(&mut self) {
+        let rest = &self.text.as_bytes()[self.at..];
+        self.at += rest
+            .iter()
+            .position(|b| !b.is_ascii_whitespace())
+            .unwrap_or(rest.len());
+    }
+}
@@ -1,18 +1,198 @@
+use std::{borrow::Cow, cell::OnceCell, collections::HashMap, io, ops::Range};
+
+use fuzzy_matcher::{skim::SkimMatcherV2, FuzzyMatcher};
+use regex::{Regex, RegexBuilder};
 use serde_json::Value;
 
-#[derive(Copy, Clone)]
+use crate::{
+    glob::Glob,
+    load,
+    path::{self, format_path, PathSegment},
+    stats::DocStats,
+};
+
+/// Rows kept visible above and below the selection when scrolling.
+const SCROLLOFF: usize = 2;
+
+/// Terminals narrower than this show only the middle panel, unless the
+/// panels are stacked.
+const COMPACT_WIDTH: u16 = 48;
+
+/// One of the panels, from left to right: ancestors of the middle panel,
+/// the middle panel holding the selection, then the preview of it.
+#[derive(Copy, Clone, PartialEq, Eq)]
 pub enum PanelSide {
-    Left,
+    /// The container `n` levels above the middle panel, 1 being its parent.
+    Ancestor(usize),
     Middle,
     Right,
 }
 
+/// How the panels share the screen.
+#[derive(Copy, Clone, Default, PartialEq, Eq)]
+pub enum Layout {
+    /// Side by side, ancestors on the left and the preview on the right.
+    #[default]
+    Vertical,
+    /// Stacked, ancestors on top and the preview at the bottom, for
+    /// terminals too narrow for columns.
+    Horizontal,
+}
+
+/// Where a panel is drawn, in terminal cells.
+#[derive(Copy, Clone)]
+pub struct Bounds {
+    pub column: u16,
+    pub top: u16,
+    pub width: u16,
+    pub height: u16,
+}
+
+/// The order object keys are listed in. Arrays always keep theirs.
+#[derive(Copy, Clone, Default, PartialEq, Eq)]
+pub enum KeyOrder {
+    /// As written in the document.
+    #[default]
+    Document,
+    Ascending,
+    Descending,
+}
+
+impl KeyOrder {
+    /// The order after this one, coming back around to `Document`.
+    pub fn next(self) -> KeyOrder {
+        match self {
+            KeyOrder::Document => KeyOrder::Ascending,
+            KeyOrder::Ascending => KeyOrder::Descending,
+            KeyOrder::Descending => KeyOrder::Document,
+        }
+    }
+}
+
+/// How letter case is compared when searching and filtering.
+#[derive(Copy, Clone, Default, PartialEq, Eq)]
+pub enum Case {
+    /// Ignored unless the query has an uppercase letter.
+    #[default]
+    Smart,
+    Insensitive,
+    Sensitive,
+}
+
+impl Case {
+    /// The mode after this one, coming back around to `Smart`.
+    pub fn next(self) -> Case {
+        match self {
+            Case::Smart => Case::Insensitive,
+            Case::Insensitive => Case::Sensitive,
+            Case::Sensitive => Case::Smart,
+        }
+    }
+
+    fn ignores_case_of(self, query: &str, regex: bool) -> bool {
+        match self {
+            Case::Smart if regex => !has_uppercase(query),
+            Case::Smart => !query.chars().any(char::is_uppercase),
+            Case::Insensitive => true,
+            Case::Sensitive => false,
+        }
+    }
+}
+
+/// Whether regex `pattern` has an uppercase letter outside of escapes like
+/// `\D`, for smart case.
+fn has_uppercase(pattern: &str) -> bool {
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            chars.next();
+        } else if c.is_uppercase() {
+            return true;
+        }
+    }
+    false
+}
+
+/// A search or filter query, prepared once for matching against entries.
+pub struct Pattern(Matcher);
+
+enum Matcher {
+    Text { text: String, ignore_case: bool },
+    Regex(Regex),
+}
+
+impl Pattern {
+    /// Fails when `regex` is set and `query` isn't a valid regular
+    /// expression.
+    fn new(query: &str, case: Case, regex: bool) -> Result<Pattern, regex::Error> {
+        let ignore_case = case.ignores_case_of(query, regex);
+        if regex {
+            return RegexBuilder::new(query)
+                .case_insensitive(ignore_case)
+                .build()
+                .map(|regex| Pattern(Matcher::Regex(regex)));
+        }
+        let text = if ignore_case {
+            query.to_lowercase()
+        } else {
+            query.to_owned()
+        };
+        Ok(Pattern(Matcher::Text { text, ignore_case }))
+    }
+
+    fn is_match(&self, text: &str) -> bool {
+        match &self.0 {
+            Matcher::Text {
+                text: query,
+                ignore_case: true,
+            } => text.to_lowercase().contains(query),
+            Matcher::Text { text: query, .. } => text.contains(query),
+            Matcher::Regex(regex) => regex.is_match(text),
+        }
+    }
+
+    /// The bytes of the first match in `text`, if it matches at all.
+    pub fn find(&self, text: &str) -> Option<Range<usize>> {
+        match &self.0 {
+            Matcher::Text {
+                text: query,
+                ignore_case: true,
+            } => text.char_indices().find_map(|(start, _)| {
+                // Lowercasing can change the length of a letter, so the query
+                // is compared a character at a time against `text` as it is.
+                let mut rest = query.chars().peekable();
+                for (offset, c) in text[start..].char_indices() {
+                    if !c.to_lowercase().all(|c| rest.next() == Some(c)) {
+                        return None;
+                    }
+                    if rest.peek().is_none() {
+                        return Some(start..start + offset + c.len_utf8());
+                    }
+                }
+                None
+            }),
+            Matcher::Text { text: query, .. } => text
+                .find(query.as_str())
+                .map(|start| start..start + query.len()),
+            Matcher::Regex(regex) => regex.find(text).map(|found| found.range()),
+        }
+    }
+}
+
 pub struct PanelState<'a> {
     value: &'a Value,
     text: String,
-    column: u16,
-    width: u16,
-    index: u16,
+    bounds: Bounds,
+    index: usize,
+    scroll_offset: usize,
+    /// Screen row of the selection counted from the top of the panel, if
+    /// it is in view.
+    row: Option<u16>,
+    /// Child indices in display order, when not simply every child in turn.
+    order: Option<&'a [usize]>,
+    children: Option<Cow<'a, Children<'a>>>,
+    selected: Option<&'a Value>,
+    key_path: Vec<String>,
 }
 
 impl<'a> PanelState<'a> {
@@ -25,128 +205,1442 @@ impl<'a> PanelState<'a> {
     }
 
     pub fn column(&self) -> u16 {
-        self.column
+        self.bounds.column
+    }
+
+    /// Screen row of the panel's first entry.
+    pub fn top(&self) -> u16 {
+        self.bounds.top
     }
 
     pub fn width(&self) -> u16 {
-        self.width
+        self.bounds.width
+    }
+
+    /// Rows the panel has to list entries on.
+    pub fn height(&self) -> u16 {
+        self.bounds.height
     }
 
-    pub fn index(&self) -> u16 {
+    /// Position of the selection among the listed entries.
+    pub fn index(&self) -> usize {
         self.index
     }
+
+    pub fn scroll_offset(&self) -> usize {
+        self.scroll_offset
+    }
+
+    pub fn row(&self) -> Option<u16> {
+        self.row
+    }
+
+    /// The child value under the highlighted entry, if the panel shows a
+    /// container.
+    pub fn selected(&self) -> Option<&Value> {
+        self.selected
+    }
+
+    /// Keys from the root to the panel's value, leaving out the groups of
+    /// folded arrays.
+    pub fn key_path(&self) -> &[String] {
+        &self.key_path
+    }
+
+    /// Number of entries the panel lists.
+    pub fn len(&self) -> usize {
+        self.order.map_or_else(
+            || {
+                self.children
+                    .as_ref()
+                    .map_or_else(|| get_value_size(self.value), |children| children.len())
+            },
+            <[usize]>::len,
+        )
+    }
+
+    /// Whether the panel lists entries even though it is the preview, as it
+    /// does for a group of array entries.
+    pub fn lists_entries(&self) -> bool {
+        self.children.is_some()
+    }
+
+    /// Whether the panel shows a container with no entries to select.
+    pub fn is_empty(&self) -> bool {
+        is_container(self.value) && self.len() == 0
+    }
+
+    /// Keys and values of up to `count` entries in display order, starting
+    /// at row `offset`. Entries outside that range are never touched. The
+    /// groups of a folded array have no value of their own.
+    pub fn rows(&self, offset: usize, count: usize) -> Vec<(String, Option<&'a Value>)> {
+        let Some(children) = &self.children else {
+            return Vec::new();
+        };
+        let end = self.len().min(offset.saturating_add(count));
+        (offset.min(end)..end)
+            .filter_map(|position| {
+                let index = self
+                    .order
+                    .map_or(Some(position), |order| order.get(position).copied())?;
+                Some((children.key(index), children.get(index)))
+            })
+            .collect()
+    }
+}
+
+/// Position and view settings detached from any document, so they can be
+/// carried over when the document is reloaded.
+#[derive(Default)]
+pub struct Snapshot {
+    keys: Vec<String>,
+    index: usize,
+    positions: HashMap<Vec<String>, usize>,
+    wrap: bool,
+    show_size: bool,
+    last_query: Option<String>,
+    key_order: KeyOrder,
+    reverse_arrays: bool,
+    case: Case,
+    regex: bool,
+    /// Left as set up for an empty snapshot, since there is no view yet to
+    /// take it from.
+    preview_depth: Option<usize>,
+    /// The `only` glob's text, or `None` as for `preview_depth`.
+    only: Option<Option<String>>,
+    marks: HashMap<char, Vec<PathSegment>>,
+}
+
+/// Everything that decides what the panels show, compared to tell whether
+/// an action changed the view.
+#[derive(PartialEq, Eq)]
+pub struct ViewKey {
+    indices: Vec<usize>,
+    index: usize,
+    scroll: usize,
+    filter: Option<String>,
+    only: Option<String>,
+    wrap: bool,
+    show_size: bool,
+    size: (u16, u16),
+    preview_depth: usize,
+    key_order: KeyOrder,
+    reverse_arrays: bool,
+    case: Case,
+    regex: bool,
+}
+
+/// How the children of a container are listed.
+#[derive(Clone)]
+enum Listing {
+    /// Every child in turn.
+    All,
+    /// An array too long to list, as groups of this many entries.
+    Groups(usize),
+    /// The array entries of one such group.
+    Slice(Range<usize>),
+}
+
+/// A container with its object keys and values collected in order, so that
+/// an entry can be reached by position without walking the map.
+#[derive(Clone)]
+struct Children<'a> {
+    value: &'a Value,
+    keys: Vec<&'a String>,
+    values: Vec<&'a Value>,
+    listing: Listing,
+}
+
+impl<'a> Children<'a> {
+    /// Lists `value`, folding it into groups if it is an array longer than
+    /// the `(threshold, size)` of `folding`.
+    fn new(value: &'a Value, folding: Option<(usize, usize)>) -> Children<'a> {
+        let (keys, values) = match value {
+            Value::Object(map) => map.iter().unzip(),
+            _ => (Vec::new(), Vec::new()),
+        };
+        let listing = match (value, folding) {
+            (Value::Array(arr), Some((threshold, size))) if arr.len() > threshold => {
+                Listing::Groups(size)
+            }
+            _ => Listing::All,
+        };
+        Children {
+            value,
+            keys,
+            values,
+            listing,
+        }
+    }
+
+    fn len(&self) -> usize {
+        match &self.listing {
+            // Only a scalar root gets here, and it has no entries to list.
+            Listing::All if !is_container(self.value) => 0,
+            Listing::All => get_value_size(self.value),
+            Listing::Groups(size) => get_value_size(self.value).div_ceil(*size),
+            Listing::Slice(range) => range.len(),
+        }
+    }
+
+    fn get(&self, index: usize) -> Option<&'a Value> {
+        match (self.value, &self.listing) {
+            (_, Listing::Groups(_)) => None,
+            (Value::Array(arr), Listing::Slice(range)) => {
+                arr.get(range.start + index).filter(|_| index < range.len())
+            }
+            (Value::Object(_), _) => self.values.get(index).copied(),
+            (Value::Array(arr), _) => arr.get(index),
+            _ => None,
+        }
+    }
+
+    fn key(&self, index: usize) -> String {
+        match (self.value, &self.listing) {
+            (_, Listing::Groups(_)) => self
+                .group_range(index)
+                .map(|range| format!("[{}..{}]", range.start, range.end - 1))
+                .unwrap_or_default(),
+            (_, Listing::Slice(range)) if index < range.len() => (range.start + index).to_string(),
+            (_, Listing::Slice(_)) => String::new(),
+            (Value::Object(_), _) => self
+                .keys
+                .get(index)
+                .map(|key| (*key).clone())
+                .unwrap_or_default(),
+            (value, _) => get_value_key(value, index),
+        }
+    }
+
+    /// Position of the entry listed as `key`.
+    fn index_of(&self, key: &str) -> Option<usize> {
+        match (self.value, &self.listing) {
+            (_, Listing::Groups(_)) => (0..self.len()).position(|index| self.key(index) == key),
+            (_, Listing::Slice(range)) => key
+                .parse::<usize>()
+                .ok()
+                .filter(|index| range.contains(index))
+                .map(|index| index - range.start),
+            (Value::Object(_), _) => self.keys.iter().position(|k| *k == key),
+            (Value::Array(arr), _) => key.parse().ok().filter(|index| *index < arr.len()),
+            _ => None,
+        }
+    }
+
+    fn is_folded(&self) -> bool {
+        matches!(self.listing, Listing::Groups(_))
+    }
+
+    /// Array indices covered by group `index` of a folded array.
+    fn group_range(&self, index: usize) -> Option<Range<usize>> {
+        let Listing::Groups(size) = self.listing else {
+            return None;
+        };
+        let len = get_value_size(self.value);
+        let start = index.checked_mul(size).filter(|start| *start < len)?;
+        Some(start..(start + size).min(len))
+    }
+
+    /// The entries of group `index`, listed as a container of their own.
+    fn group(&self, index: usize) -> Option<Children<'a>> {
+        Some(Children {
+            value: self.value,
+            keys: Vec::new(),
+            values: Vec::new(),
+            listing: Listing::Slice(self.group_range(index)?),
+        })
+    }
 }
 
 pub struct ProgramState<'a> {
     size: (u16, u16),
     value: &'a Value,
     index: usize,
+    scroll: usize,
     paths: Vec<String>,
     values: Vec<&'a Value>,
     indices: Vec<usize>,
+    /// Lookup tables for each of `values` followed by `value`. A folded
+    /// array appears twice in a row, once listing its groups and then
+    /// listing the group that was entered.
+    children: Vec<Children<'a>>,
+    wrap: bool,
+    /// Shows how many bytes the selection takes up as JSON.
+    show_size: bool,
+    last_query: Option<String>,
+    /// The selection last left in each container visited, keyed by its
+    /// path from the root.
+    positions: HashMap<Vec<String>, usize>,
+    /// Selections remembered under a letter, to go back to.
+    marks: HashMap<char, Vec<PathSegment>>,
+    /// Narrows the middle panel to entries whose key contains this text.
+    filter: Option<String>,
+    /// Narrows the middle panel, at every level, to the branches whose
+    /// dotted key path this matches.
+    only: Option<Glob>,
+    /// Relative width of each panel across the screen, or height when
+    /// they are stacked.
+    weights: Vec<u16>,
+    layout: Layout,
+    /// Shows only the middle panel, whatever the width.
+    compact: bool,
+    /// Child indices of the middle panel in display order, when not simply
+    /// every child in turn.
+    order: Option<Vec<usize>>,
+    /// How objects in the middle panel list their keys.
+    key_order: KeyOrder,
+    /// Lists arrays in the middle panel last entry first.
+    reverse_arrays: bool,
+    /// How searching, filtering and finding compare letter case.
+    case: Case,
+    /// Takes search and filter queries as regular expressions rather than
+    /// text to look for.
+    regex: bool,
+    /// Arrays longer than the first number are listed as groups of the
+    /// second number of entries.
+    folding: Option<(usize, usize)>,
+    /// Shows strings in the preview as their text rather than as JSON.
+    decode_strings: bool,
+    /// Nesting levels the preview expands before summarizing containers.
+    preview_depth: usize,
+    /// One level of indent in pretty JSON.
+    indent: String,
+    /// Lists the values of an array of scalars in the preview, one a row.
+    preview_values: bool,
+    /// Worked out the first time it is asked for.
+    stats: OnceCell<DocStats>,
 }
 
 impl<'a> ProgramState<'a> {
-    pub fn new(value: &'a Value, size: (u16, u16)) -> ProgramState {
+    /// Starts at the root of `value` laid out for a `size` of (columns,
+    /// rows). Nothing here touches the terminal, so any size will do when
+    /// driving the state without one.
+    pub fn new(value: &'a Value, size: (u16, u16)) -> ProgramState<'a> {
         ProgramState {
             size,
             value,
             index: 0,
+            scroll: 0,
             paths: Vec::new(),
             values: Vec::new(),
             indices: Vec::new(),
+            children: vec![Children::new(value, None)],
+            wrap: false,
+            show_size: false,
+            last_query: None,
+            positions: HashMap::new(),
+            marks: HashMap::new(),
+            filter: None,
+            only: None,
+            weights: vec![1; 3],
+            layout: Layout::Vertical,
+            compact: false,
+            order: None,
+            key_order: KeyOrder::Document,
+            reverse_arrays: false,
+            case: Case::Smart,
+            regex: false,
+            folding: None,
+            decode_strings: true,
+            preview_depth: 2,
+            preview_values: false,
+            indent: "  ".to_owned(),
+            stats: OnceCell::new(),
+        }
+    }
+
+    pub fn view_key(&self) -> ViewKey {
+        ViewKey {
+            indices: self.indices.clone(),
+            index: self.index,
+            scroll: self.scroll,
+            filter: self.filter.clone(),
+            only: self.only().map(str::to_owned),
+            wrap: self.wrap,
+            show_size: self.show_size,
+            size: self.size,
+            preview_depth: self.preview_depth,
+            key_order: self.key_order,
+            reverse_arrays: self.reverse_arrays,
+            case: self.case,
+            regex: self.regex,
+        }
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            keys: self.paths.clone(),
+            index: self.index,
+            positions: self.positions.clone(),
+            wrap: self.wrap,
+            show_size: self.show_size,
+            last_query: self.last_query.clone(),
+            key_order: self.key_order,
+            reverse_arrays: self.reverse_arrays,
+            case: self.case,
+            regex: self.regex,
+            preview_depth: Some(self.preview_depth),
+            only: Some(self.only().map(str::to_owned)),
+            marks: self.marks.clone(),
         }
     }
 
+    /// Re-walks the snapshot's path into this document, staying at the root
+    /// if any step of it no longer exists.
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        self.wrap = snapshot.wrap;
+        self.show_size = snapshot.show_size;
+        self.key_order = snapshot.key_order;
+        self.reverse_arrays = snapshot.reverse_arrays;
+        self.case = snapshot.case;
+        self.regex = snapshot.regex;
+        if let Some(depth) = snapshot.preview_depth {
+            self.preview_depth = depth;
+        }
+        if let Some(only) = &snapshot.only {
+            self.only = only.as_deref().and_then(|glob| Glob::new(glob).ok());
+        }
+        self.last_query.clone_from(&snapshot.last_query);
+        self.positions.clone_from(&snapshot.positions);
+        self.marks.clone_from(&snapshot.marks);
+        for key in &snapshot.keys {
+            match self.entries().index_of(key) {
+                Some(index) => {
+                    self.index = index;
+                    self.push_one();
+                }
+                None => {
+                    self.pop_path(self.paths.len());
+                    self.index = 0;
+                    self.update_order();
+                    self.update_scroll();
+                    return;
+                }
+            }
+        }
+        self.index = snapshot.index.min(self.entries().len().saturating_sub(1));
+        self.update_order();
+        self.update_scroll();
+    }
+
     pub fn path_string(&self) -> String {
         let base = if self.paths.len() > 6 {
             ".../".to_owned() + &self.paths.last_chunk::<6>().unwrap().join("/")
         } else {
             self.paths.join("/")
         };
-        base + "/" + &get_value_key(self.value, self.index)
+        if self.visible_len() == 0 {
+            return base + "/";
+        }
+        base + "/" + &self.entries().key(self.index)
+    }
+
+    /// The jq-style path of the current selection, e.g. `.foo.bar[3]`.
+    pub fn current_path(&self) -> String {
+        format_path(&self.path_segments())
+    }
+
+    /// Whether the whole document is one scalar, leaving nothing to
+    /// navigate.
+    pub fn root_is_scalar(&self) -> bool {
+        !is_container(self.root())
+    }
+
+    /// The container listed in the middle panel.
+    pub fn current(&self) -> &'a Value {
+        self.value
     }
 
-    pub fn panel_state(&self, panel_side: PanelSide) -> Option<PanelState> {
-        let (cols, _) = self.size;
-        let width = cols / 3;
+    /// The middle panel's container by the name it was entered through,
+    /// with its entry count, such as `items [128]` or `config {17}`. The
+    /// root goes by `.`.
+    pub fn focus_label(&self) -> String {
+        let name = self.paths.last().map_or(".", String::as_str);
+        let len = self.entries().len();
+        match self.value {
+            Value::Object(_) => format!("{name} {{{len}}}"),
+            Value::Array(_) => format!("{name} [{len}]"),
+            _ => name.to_owned(),
+        }
+    }
+
+    /// How many levels below the root the middle panel is.
+    pub fn depth(&self) -> usize {
+        self.paths.len()
+    }
+
+    /// The node under the selection, or the middle panel's value when it
+    /// has nothing to select.
+    pub fn current_subtree(&self) -> &'a Value {
+        if self.visible_len() == 0 {
+            return self.value;
+        }
+        self.entries().get(self.index).unwrap_or(self.value)
+    }
+
+    /// Bytes the selection takes up as compact JSON, worked out anew on
+    /// every call.
+    pub fn selected_byte_size(&self) -> usize {
+        let mut counter = ByteCounter(0);
+        // Neither a `Value` nor the counter can fail to write.
+        let _ = serde_json::to_writer(&mut counter, self.current_subtree());
+        counter.0
+    }
+
+    /// The JSON type of the selected value, with the entry count for
+    /// containers, such as `object (3)`.
+    pub fn selected_type_label(&self) -> String {
+        if let Some(group) = self.entries().group(self.index) {
+            return format!("group ({})", group.len());
+        }
+        match self.current_subtree() {
+            Value::String(_) => "string".to_owned(),
+            Value::Number(_) => "number".to_owned(),
+            Value::Bool(_) => "boolean".to_owned(),
+            Value::Null => "null".to_owned(),
+            Value::Object(map) => format!("object ({})", map.len()),
+            Value::Array(arr) => format!("array ({})", arr.len()),
+        }
+    }
+
+    /// Remembers the current selection as mark `name`, replacing any mark
+    /// of that name.
+    pub fn set_mark(&mut self, name: char) {
+        self.marks.insert(name, self.path_segments());
+    }
+
+    /// The path of the selection marked `name`.
+    pub fn mark(&self, name: char) -> Option<&[PathSegment]> {
+        self.marks.get(&name).map(Vec::as_slice)
+    }
+
+    /// Steps from the root to the current selection. Entering a group of a
+    /// folded array is no step in the document, so it is left out.
+    pub fn path_segments(&self) -> Vec<PathSegment> {
+        let mut path: Vec<PathSegment> = self
+            .values
+            .iter()
+            .zip(&self.paths)
+            .zip(&self.children)
+            .filter(|(_, children)| !children.is_folded())
+            .map(|((parent, key), _)| segment(parent, key))
+            .collect();
+        if self.visible_len() > 0 && is_container(self.value) && !self.entries().is_folded() {
+            path.push(segment(self.value, &self.entries().key(self.index)));
+        }
+        path
+    }
+
+    /// The whole document, however deep the view has gone.
+    pub fn root(&self) -> &'a Value {
+        self.values.first().copied().unwrap_or(self.value)
+    }
+
+    /// Size and shape of the whole document, walked on the first call only.
+    pub fn stats(&self) -> &DocStats {
+        self.stats.get_or_init(|| DocStats::of(self.root()))
+    }
+
+    /// Selects the node at `path` from the root, so that its parent becomes
+    /// the middle panel. If a segment doesn't exist, returns its position
+    /// and leaves the view where it was.
+    pub fn goto_path(&mut self, path: &[PathSegment]) -> Result<(), usize> {
+        let mut node = self.root();
+        let mut indices = Vec::with_capacity(path.len());
+        for (position, step) in path.iter().enumerate() {
+            let found = match (node, step) {
+                (Value::Object(map), PathSegment::Key(key)) => map
+                    .iter()
+                    .enumerate()
+                    .find(|(_, (k, _))| *k == key)
+                    .map(|(index, (_, child))| (index, child)),
+                (Value::Array(arr), PathSegment::Index(index)) => {
+                    arr.get(*index).map(|child| (*index, child))
+                }
+                _ => None,
+            };
+            let (index, child) = found.ok_or(position)?;
+            indices.push(index);
+            node = child;
+        }
 
-        let column = match panel_side {
-            PanelSide::Left => 0,
-            PanelSide::Middle => width,
-            PanelSide::Right => width * 2,
+        let keys: Vec<String> = path
+            .iter()
+            .map(|step| match step {
+                PathSegment::Key(key) => key.clone(),
+                PathSegment::Index(index) => index.to_string(),
+            })
+            .collect();
+        if self
+            .only
+            .as_ref()
+            .is_some_and(|only| !only.shows(&keys, is_container(node)))
+        {
+            self.only = None;
+        }
+        self.pop_path(self.paths.len());
+        self.set_filter(None);
+        let Some((last, parents)) = indices.split_last() else {
+            self.goto(0);
+            return Ok(());
         };
+        for index in parents {
+            self.select_child(*index);
+            self.push_one();
+        }
+        self.select_child(*last);
+        Ok(())
+    }
+
+    /// Selects child `index` of the middle panel's container, entering the
+    /// group that holds it if the container is folded.
+    fn select_child(&mut self, index: usize) {
+        if let Listing::Groups(size) = self.entries().listing {
+            self.index = index / size;
+            self.push_one();
+            self.goto(index % size);
+        } else {
+            self.goto(index);
+        }
+    }
 
+    /// Walks the whole document for entries whose key or scalar value
+    /// fuzzy-matches `query`, returning their paths best match first.
+    pub fn find_all(&self, query: &str) -> Vec<Vec<PathSegment>> {
+        if query.is_empty() {
+            return Vec::new();
+        }
+        let matcher = match self.case {
+            Case::Smart => SkimMatcherV2::default().smart_case(),
+            Case::Insensitive => SkimMatcherV2::default().ignore_case(),
+            Case::Sensitive => SkimMatcherV2::default().respect_case(),
+        };
+        let mut found = Vec::new();
+        path::walk(self.root(), &mut Vec::new(), &mut |path, child| {
+            let key_score = match path.last() {
+                Some(PathSegment::Key(key)) => matcher.fuzzy_match(key, query),
+                _ => None,
+            };
+            let value_score = match child {
+                Value::Object(_) | Value::Array(_) => None,
+                Value::String(s) => matcher.fuzzy_match(s, query),
+                scalar => matcher.fuzzy_match(&load::scalar_text(scalar), query),
+            };
+            if let Some(score) = key_score.max(value_score) {
+                found.push((score, path.to_vec()));
+            }
+        });
+        // A stable sort keeps equally good matches in document order.
+        found.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        found.into_iter().map(|(_, path)| path).collect()
+    }
+
+    pub fn panel_state(&self, panel_side: PanelSide) -> Option<PanelState<'_>> {
+        let slot = self
+            .panel_sides()
+            .iter()
+            .position(|side| *side == panel_side)?;
+        let bounds = self.panel_bounds()[slot];
+        let height = usize::from(bounds.height);
+
+        let ancestor = match panel_side {
+            PanelSide::Ancestor(level) => self.values.len().checked_sub(level),
+            _ => None,
+        };
         let index = match panel_side {
-            PanelSide::Left => *self.indices.last()?,
+            PanelSide::Ancestor(_) => self.indices[ancestor?],
             PanelSide::Middle => self.index,
             PanelSide::Right => 0,
         };
+        let position = match panel_side {
+            PanelSide::Middle => self.position(),
+            _ => index,
+        };
 
+        if let (PanelSide::Right, Some(group)) = (panel_side, self.entries().group(self.index)) {
+            return Some(PanelState {
+                value: self.value,
+                text: String::new(),
+                bounds,
+                index: 0,
+                scroll_offset: 0,
+                row: None,
+                order: None,
+                children: Some(Cow::Owned(group)),
+                selected: None,
+                key_path: self.key_path(self.paths.len()),
+            });
+        }
         let value = match panel_side {
-            PanelSide::Left => self.values.last()?,
+            PanelSide::Ancestor(_) => self.values[ancestor?],
             PanelSide::Middle => self.value,
-            PanelSide::Right => match self.value {
-                Value::Object(map) => map.values().nth(self.index)?,
-                Value::Array(arr) => arr.get(self.index)?,
-                _ => return None,
-            },
+            PanelSide::Right if self.visible_len() == 0 => return None,
+            PanelSide::Right => self.entries().get(self.index)?,
+        };
+        let order = match panel_side {
+            PanelSide::Middle => self.order.as_deref(),
+            _ => None,
+        };
+        // The preview has no rows to look up.
+        let children = match panel_side {
+            PanelSide::Ancestor(_) => Some(&self.children[ancestor?]),
+            PanelSide::Middle => Some(self.entries()),
+            PanelSide::Right => None,
+        };
+        let selected = match (panel_side, children) {
+            (PanelSide::Middle, _) if self.visible_len() == 0 => None,
+            (_, Some(children)) => children.get(index),
+            (_, None) => child(value, index),
+        };
+
+        let scroll_offset = match (panel_side, children) {
+            (PanelSide::Ancestor(_), Some(children)) => scroll_for(index, children.len(), height),
+            (PanelSide::Middle, _) => self.scroll,
+            _ => 0,
         };
 
-        let text = get_value_key(value, index);
+        let key_path = match panel_side {
+            PanelSide::Ancestor(_) => self.key_path(ancestor?),
+            PanelSide::Middle => self.key_path(self.paths.len()),
+            PanelSide::Right => {
+                let mut path = self.key_path(self.paths.len());
+                path.push(self.entries().key(self.index));
+                path
+            }
+        };
+
+        let text = match children {
+            Some(children) if children.is_folded() => children.key(index),
+            _ if selected.is_none() && is_container(value) => String::new(),
+            Some(children) => children.key(index),
+            None => get_value_key(value, index),
+        };
 
         Some(PanelState {
             value,
             text,
-            column,
-            width,
-            index: index.try_into().unwrap(),
+            bounds,
+            index: position,
+            scroll_offset,
+            row: position
+                .checked_sub(scroll_offset)
+                .filter(|row| *row < height)
+                .and_then(|row| u16::try_from(row).ok()),
+            order,
+            children: children.map(Cow::Borrowed),
+            selected,
+            key_path,
         })
     }
 
+    /// Maps a terminal cell to the panel drawn there, going by the column
+    /// side by side and by the row when stacked.
+    pub fn panel_side_at(&self, column: u16, row: u16) -> Option<PanelSide> {
+        let slot = self
+            .panel_bounds()
+            .iter()
+            .position(|bounds| match self.layout {
+                Layout::Vertical => (bounds.column..bounds.column + bounds.width).contains(&column),
+                Layout::Horizontal => (bounds.top..bounds.top + bounds.height).contains(&row),
+            })?;
+        Some(self.panel_sides()[slot])
+    }
+
+    /// Number of panels shown, which is one when compact.
+    pub fn columns(&self) -> usize {
+        self.weights().len()
+    }
+
+    fn weights(&self) -> &[u16] {
+        if self.is_compact() {
+            &[1]
+        } else {
+            &self.weights
+        }
+    }
+
+    /// Whether only the middle panel is shown, as it is when asked for or
+    /// when the terminal is too narrow for panels side by side.
+    pub fn is_compact(&self) -> bool {
+        self.compact || (self.layout == Layout::Vertical && self.size.0 < COMPACT_WIDTH)
+    }
+
+    pub fn set_compact(&mut self, compact: bool) {
+        self.compact = compact;
+        self.update_scroll();
+    }
+
+    /// Sets how many panels share the width equally, at least the middle
+    /// panel and the preview.
+    pub fn set_columns(&mut self, columns: usize) {
+        self.weights = vec![1; columns.max(2)];
+    }
+
+    /// Lists arrays longer than `threshold` as groups of `size` entries, or
+    /// every array in full for a `threshold` of 0. Starts over at the root.
+    pub fn set_folding(&mut self, threshold: usize, size: usize) {
+        self.folding = (threshold > 0).then_some((threshold, size.max(1)));
+        self.pop_path(self.paths.len());
+        self.children = vec![Children::new(self.value, self.folding)];
+        self.set_filter(None);
+        self.goto(0);
+    }
+
+    /// Shares the width, or the height when stacked, between one panel per
+    /// weight, in proportion to it. Ignored unless there are weights for at
+    /// least two panels.
+    pub fn set_widths(&mut self, weights: &[u16]) {
+        if weights.len() >= 2 && weights.iter().all(|weight| *weight > 0) {
+            self.weights = weights.to_vec();
+        }
+    }
+
+    pub fn layout(&self) -> Layout {
+        self.layout
+    }
+
+    /// Lays the panels out side by side or stacked, scrolling as needed to
+    /// keep the selection in view.
+    pub fn set_layout(&mut self, layout: Layout) {
+        self.layout = layout;
+        self.update_scroll();
+    }
+
+    /// The panels from left to right, or top to bottom when stacked.
+    pub fn panel_sides(&self) -> Vec<PanelSide> {
+        if self.is_compact() {
+            return vec![PanelSide::Middle];
+        }
+        let ancestors = (1..self.columns() - 1).rev().map(PanelSide::Ancestor);
+        ancestors
+            .chain([PanelSide::Middle, PanelSide::Right])
+            .collect()
+    }
+
+    /// Columns of the dividers drawn between neighbouring panels, or rows
+    /// when they are stacked.
+    pub fn dividers(&self) -> Vec<u16> {
+        let bounds = self.panel_bounds();
+        bounds[..bounds.len() - 1]
+            .iter()
+            .map(|bounds| match self.layout {
+                Layout::Vertical => bounds.column + bounds.width,
+                Layout::Horizontal => bounds.top + bounds.height,
+            })
+            .collect()
+    }
+
+    /// Where each panel is drawn, once a column, or a row when stacked, is
+    /// reserved for each divider. The last panel takes up what rounding
+    /// leaves.
+    pub fn panel_bounds(&self) -> Vec<Bounds> {
+        let dividers = self.columns() - 1;
+        let length = match self.layout {
+            Layout::Vertical => usize::from(self.size.0),
+            Layout::Horizontal => self.view_height(),
+        };
+        let available = length.saturating_sub(dividers);
+        let total: usize = self
+            .weights()
+            .iter()
+            .map(|weight| usize::from(*weight))
+            .sum();
+        let cells = |n: usize| u16::try_from(n).unwrap_or(u16::MAX);
+
+        let mut bounds = Vec::with_capacity(self.columns());
+        let mut start = 0;
+        let mut used = 0;
+        for (slot, weight) in self.weights().iter().enumerate() {
+            let extent = if slot == dividers {
+                available - used
+            } else {
+                available * usize::from(*weight) / total
+            };
+            bounds.push(match self.layout {
+                Layout::Vertical => Bounds {
+                    column: cells(start),
+                    top: 1,
+                    width: cells(extent),
+                    height: cells(self.view_height()),
+                },
+                Layout::Horizontal => Bounds {
+                    column: 0,
+                    top: cells(start + 1),
+                    width: self.size.0,
+                    height: cells(extent),
+                },
+            });
+            start += extent + 1;
+            used += extent;
+        }
+        bounds
+    }
+
+    /// Selects the entry drawn on terminal `row` of the given panel,
+    /// ascending so that it ends up in the middle panel. The right panel is
+    /// a free-form preview, so clicking anywhere in it descends into the
+    /// previewed value instead.
+    pub fn select_at(&mut self, panel_side: PanelSide, row: u16) {
+        if let PanelSide::Right = panel_side {
+            if self
+                .panel_state(PanelSide::Right)
+                .is_some_and(|p| is_container(p.value()))
+            {
+                self.push_one();
+            }
+            return;
+        }
+        let Some(panel) = self.panel_state(panel_side) else {
+            return;
+        };
+        let Some(row) = row.checked_sub(panel.top()).map(usize::from) else {
+            return;
+        };
+        if row >= usize::from(panel.height()) {
+            return;
+        }
+        if !is_container(panel.value()) {
+            return;
+        }
+        let position = row + panel.scroll_offset();
+        if position >= panel.len() {
+            return;
+        }
+
+        if let PanelSide::Ancestor(level) = panel_side {
+            self.pop_path(level);
+        }
+        self.select_position(position);
+    }
+
+    /// Number of terminal rows available to the panels, between the path
+    /// line and the status bar.
+    pub fn view_height(&self) -> usize {
+        usize::from(self.size.1.saturating_sub(2))
+    }
+
+    /// Rows the middle panel lists entries on, which is all of the view
+    /// height unless the panels are stacked.
+    pub fn list_height(&self) -> usize {
+        let slot = self
+            .panel_sides()
+            .iter()
+            .position(|side| *side == PanelSide::Middle)
+            .unwrap_or(0);
+        usize::from(self.panel_bounds()[slot].height)
+    }
+
     pub fn size(&self) -> (u16, u16) {
         self.size
     }
 
+    /// Lays out for a new terminal size, scrolling as needed to keep the
+    /// selection in view.
     pub fn resize(&mut self, size: (u16, u16)) {
         self.size = size;
+        self.update_scroll();
     }
 
-    pub fn push_path(&mut self) {
-        let value = match self.value {
-            Value::Object(map) => map.values().nth(self.index),
-            Value::Array(arr) => arr.get(self.index),
-            _ => None,
-        };
+    pub fn push_path(&mut self, count: usize) {
+        for _ in 0..count {
+            self.push_one();
+        }
+    }
 
-        if let Some(val) = value {
-            self.indices.push(self.index);
-            self.values.push(self.value);
-            let text = get_value_key(self.value, self.index);
-            self.paths.push(text);
+    pub fn pop_path(&mut self, count: usize) {
+        for _ in 0..count {
+            self.pop_one();
+        }
+    }
+
+    /// Lookup table for the middle panel's container.
+    fn entries(&self) -> &Children<'a> {
+        // There is always one for `value` after those for `values`.
+        &self.children[self.values.len()]
+    }
 
-            self.index = 0;
-            self.value = val;
+    fn push_one(&mut self) {
+        if self.visible_len() == 0 {
+            return;
+        }
+
+        // A group stays within the same array, listing less of it.
+        if let Some(group) = self.entries().group(self.index) {
+            self.descend(self.value, group);
+        } else if let Some(val) = self.entries().get(self.index) {
+            self.descend(val, Children::new(val, self.folding));
         }
     }
 
-    pub fn pop_path(&mut self) {
+    fn descend(&mut self, value: &'a Value, children: Children<'a>) {
+        self.positions.insert(self.paths.clone(), self.index);
+        let text = self.entries().key(self.index);
+        self.indices.push(self.index);
+        self.values.push(self.value);
+        self.paths.push(text);
+        self.children.push(children);
+
+        // The container may have shrunk since, after a reload.
+        let remembered = self.positions.get(&self.paths).copied().unwrap_or(0);
+        self.index = remembered.min(self.entries().len().saturating_sub(1));
+        self.value = value;
+        self.scroll = 0;
+        self.filter = None;
+        self.update_order();
+        self.update_scroll();
+    }
+
+    fn pop_one(&mut self) {
         if !self.paths.is_empty() {
+            self.positions.insert(self.paths.clone(), self.index);
             self.index = self.indices.pop().unwrap();
             self.value = self.values.pop().unwrap();
             self.paths.pop();
+            self.children.pop();
+            self.filter = None;
+            self.update_order();
+            self.scroll = scroll_for(self.position(), self.visible_len(), self.list_height());
+        }
+    }
+
+    pub fn inc_index(&mut self, count: usize) {
+        let size = self.visible_len();
+        let position = self.position();
+        if self.wrap && size > 1 {
+            self.select_position((position + count % size) % size);
+        } else {
+            self.select_position(position.saturating_add(count).min(size.saturating_sub(1)));
+        }
+    }
+
+    pub fn dec_index(&mut self, count: usize) {
+        let size = self.visible_len();
+        let position = self.position();
+        if self.wrap && size > 1 {
+            self.select_position((position + size - count % size) % size);
+        } else {
+            self.select_position(position.saturating_sub(count));
+        }
+    }
+
+    pub fn filter(&self) -> Option<&str> {
+        self.filter.as_deref()
+    }
+
+    /// Shows only the middle panel entries whose key contains `filter`, or
+    /// every entry again for `None`. The selection is kept if it stays
+    /// visible.
+    pub fn set_filter(&mut self, filter: Option<String>) {
+        self.filter = filter.filter(|filter| !filter.is_empty());
+        self.update_order();
+        self.update_scroll();
+    }
+
+    /// The glob given to `set_only`, as it was written.
+    pub fn only(&self) -> Option<&str> {
+        self.only.as_ref().map(Glob::as_str)
+    }
+
+    /// Shows only the branches whose dotted key path matches `glob`, such
+    /// as `spec.*`, at every level, or everything again for `None` or a
+    /// glob that doesn't compile. The selection is kept if it stays visible.
+    pub fn set_only(&mut self, glob: Option<&str>) {
+        self.only = glob.and_then(|glob| Glob::new(glob).ok());
+        self.update_order();
+        self.update_scroll();
+    }
+
+    pub fn case(&self) -> Case {
+        self.case
+    }
+
+    /// Compares letter case as `case` says from here on, re-applying the
+    /// filter.
+    pub fn set_case(&mut self, case: Case) {
+        self.case = case;
+        self.set_filter(self.filter.clone());
+    }
+
+    pub fn regex(&self) -> bool {
+        self.regex
+    }
+
+    /// Switches between regular expression and plain text queries,
+    /// re-applying the filter.
+    pub fn toggle_regex(&mut self) {
+        self.regex = !self.regex;
+        self.set_filter(self.filter.clone());
+    }
+
+    /// `query` prepared to match as searches and filters do, unless it is a
+    /// bad regex.
+    pub fn pattern(&self, query: &str) -> Option<Pattern> {
+        Pattern::new(query, self.case, self.regex).ok()
+    }
+
+    /// What is wrong with `query` as a search or filter, if anything.
+    pub fn query_error(&self, query: &str) -> Option<regex::Error> {
+        Pattern::new(query, self.case, self.regex).err()
+    }
+
+    pub fn key_order(&self) -> KeyOrder {
+        self.key_order
+    }
+
+    /// Lists object keys in `key_order` from here on, keeping the selection.
+    /// The document itself is left as it is.
+    pub fn set_key_order(&mut self, key_order: KeyOrder) {
+        self.key_order = key_order;
+        self.update_order();
+        self.update_scroll();
+    }
+
+    pub fn reverse_arrays(&self) -> bool {
+        self.reverse_arrays
+    }
+
+    /// Lists arrays last entry first, or first entry first again, keeping
+    /// the selection. Entries keep their own indices as labels.
+    pub fn toggle_reverse_arrays(&mut self) {
+        self.reverse_arrays = !self.reverse_arrays;
+        self.update_order();
+        self.update_scroll();
+    }
+
+    /// Works out the middle panel's display order from the filters, the key
+    /// order and whether arrays are reversed, moving the selection to the
+    /// first entry listed if it no longer is.
+    fn update_order(&mut self) {
+        let entries = self.entries();
+        let sorted = self.key_order != KeyOrder::Document && matches!(self.value, Value::Object(_));
+        let reversed = self.reverse_arrays && matches!(self.value, Value::Array(_));
+        let mut order: Option<Vec<usize>> = match self.filter.as_deref() {
+            None if !sorted && !reversed && self.only.is_none() => None,
+            None => Some((0..entries.len()).collect()),
+            // One that doesn't compile yet, half typed, leaves every entry.
+            Some(filter) => match Pattern::new(filter, self.case, self.regex) {
+                Ok(pattern) => Some(filtered_indices(entries, &pattern)),
+                Err(_) => Some((0..entries.len()).collect()),
+            },
+        };
+        // The groups of a folded array are no keys of the document, so they
+        // are all kept for what they hold.
+        if let (Some(only), Some(order)) = (&self.only, &mut order) {
+            if !entries.is_folded() && is_container(self.value) {
+                let mut path = self.key_path(self.paths.len());
+                order.retain(|index| {
+                    path.push(entries.key(*index));
+                    let container = entries.get(*index).is_some_and(is_container);
+                    let shown = only.shows(&path, container);
+                    path.pop();
+                    shown
+                });
+            }
+        }
+        if let (true, Some(order)) = (sorted, &mut order) {
+            order.sort_by(|a, b| entries.keys[*a].cmp(entries.keys[*b]));
+            if self.key_order == KeyOrder::Descending {
+                order.reverse();
+            }
+        }
+        if let (true, Some(order)) = (reversed, &mut order) {
+            order.reverse();
+        }
+        if let Some(order) = &order {
+            if !order.contains(&self.index) {
+                self.index = order.first().copied().unwrap_or(0);
+            }
+        }
+        self.order = order;
+    }
+
+    /// Keys from the root to the container `depth` levels down on the way to
+    /// the middle panel, leaving out the groups of folded arrays as
+    /// `path_segments` does.
+    fn key_path(&self, depth: usize) -> Vec<String> {
+        self.paths[..depth]
+            .iter()
+            .zip(&self.children)
+            .filter(|(_, children)| !children.is_folded())
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+
+    /// Number of entries listed in the middle panel.
+    fn visible_len(&self) -> usize {
+        self.order
+            .as_ref()
+            .map_or_else(|| self.entries().len(), Vec::len)
+    }
+
+    /// Row of the selection within the middle panel's display order.
+    fn position(&self) -> usize {
+        match &self.order {
+            Some(order) => order.iter().position(|i| *i == self.index).unwrap_or(0),
+            None => self.index,
+        }
+    }
+
+    fn index_at(&self, position: usize) -> Option<usize> {
+        match &self.order {
+            Some(order) => order.get(position).copied(),
+            None => Some(position),
+        }
+    }
+
+    /// Selects the entry listed `position`th in the middle panel, if there
+    /// is one.
+    pub fn select_position(&mut self, position: usize) {
+        if let Some(index) = self.index_at(position) {
+            self.index = index;
+        }
+        self.update_scroll();
+    }
+
+    pub fn wrap(&self) -> bool {
+        self.wrap
+    }
+
+    pub fn toggle_wrap(&mut self) {
+        self.wrap = !self.wrap;
+    }
+
+    pub fn show_size(&self) -> bool {
+        self.show_size
+    }
+
+    pub fn toggle_show_size(&mut self) {
+        self.show_size = !self.show_size;
+    }
+
+    pub fn preview_depth(&self) -> usize {
+        self.preview_depth
+    }
+
+    /// Sets how many levels of nesting the preview shows before containers
+    /// are summarized as `{…}` or `[…]`.
+    pub fn set_preview_depth(&mut self, depth: usize) {
+        self.preview_depth = depth;
+    }
+
+    pub fn indent(&self) -> &str {
+        &self.indent
+    }
+
+    /// Sets what each level of pretty JSON is indented by, previewed or
+    /// written out.
+    pub fn set_indent(&mut self, indent: &str) {
+        indent.clone_into(&mut self.indent);
+    }
+
+    pub fn preview_values(&self) -> bool {
+        self.preview_values
+    }
+
+    /// Whether the preview lists an array of scalars by its values alone,
+    /// instead of as JSON or, for a group of a folded array, by index.
+    pub fn set_preview_values(&mut self, values: bool) {
+        self.preview_values = values;
+    }
+
+    pub fn decode_strings(&self) -> bool {
+        self.decode_strings
+    }
+
+    /// Whether the preview shows strings with their escapes decoded, line
+    /// breaks and all, or exactly as written in JSON.
+    pub fn set_decode_strings(&mut self, decode: bool) {
+        self.decode_strings = decode;
+    }
+
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn goto(&mut self, index: usize) {
+        self.index = index.min(self.entries().len().saturating_sub(1));
+        self.update_scroll();
+    }
+
+    /// Finds the first entry at or after the selection, wrapping around,
+    /// whose key or scalar value contains `query`.
+    pub fn search(&self, query: &str) -> Option<usize> {
+        self.find_match(query, 0, true)
+    }
+
+    /// Remembers `query` for `next_match` and `prev_match`.
+    pub fn last_query(&self) -> Option<&str> {
+        self.last_query.as_deref()
+    }
+
+    pub fn set_last_query(&mut self, query: String) {
+        self.last_query = Some(query);
+    }
+
+    /// Selects the next entry after the selection matching the last search.
+    /// Returns whether there was one.
+    pub fn next_match(&mut self) -> bool {
+        self.jump_to_match(true)
+    }
+
+    /// Selects the closest entry before the selection matching the last
+    /// search. Returns whether there was one.
+    pub fn prev_match(&mut self) -> bool {
+        self.jump_to_match(false)
+    }
+
+    /// Selects the next listed entry after the selection, wrapping around,
+    /// whose key starts with `letter`. Case is compared as searches do.
+    /// Returns whether there was one.
+    pub fn jump_to_letter(&mut self, letter: char) -> bool {
+        let ignore_case = self.case.ignores_case_of(&letter.to_string(), false);
+        let size = self.visible_len();
+        let position = self.position();
+        let found = (1..=size)
+            .filter_map(|step| self.index_at((position + step) % size))
+            .find(|index| {
+                let first = self.entries().key(*index).chars().next();
+                first.is_some_and(|first| {
+                    first == letter || ignore_case && first.to_lowercase().eq(letter.to_lowercase())
+                })
+            });
+        match found {
+            Some(index) => {
+                self.goto(index);
+                true
+            }
+            None => false,
         }
     }
 
-    pub fn inc_index(&mut self) {
-        if self.index < get_value_size(self.value) - 1 {
-            self.index += 1;
+    fn jump_to_match(&mut self, forward: bool) -> bool {
+        let Some(query) = &self.last_query else {
+            return false;
+        };
+        match self.find_match(query, 1, forward) {
+            Some(index) => {
+                self.goto(index);
+                true
+            }
+            None => false,
         }
     }
 
-    pub fn dec_index(&mut self) {
-        self.index = self.index.saturating_sub(1);
+    /// Scans listed entries starting `skip` steps from the selection in the
+    /// given direction, wrapping around the container.
+    fn find_match(&self, query: &str, skip: usize, forward: bool) -> Option<usize> {
+        let pattern = Pattern::new(query, self.case, self.regex).ok()?;
+        let size = self.visible_len();
+        let position = self.position();
+        (skip..size + skip)
+            .map(|step| {
+                if forward {
+                    (position + step) % size
+                } else {
+                    (position + size - step % size) % size
+                }
+            })
+            .filter_map(|position| self.index_at(position))
+            .find(|index| self.entry_matches(*index, &pattern))
+    }
+
+    fn entry_matches(&self, index: usize, pattern: &Pattern) -> bool {
+        if !is_container(self.value) {
+            return false;
+        }
+        let Some(value) = self.entries().get(index) else {
+            return false;
+        };
+        let key = self.entries().key(index);
+        let scalar = match value {
+            Value::Object(_) | Value::Array(_) => String::new(),
+            Value::String(s) => s.clone(),
+            scalar => load::scalar_text(scalar),
+        };
+        pattern.is_match(&key) || pattern.is_match(&scalar)
+    }
+
+    pub fn goto_first(&mut self) {
+        self.select_position(0);
+    }
+
+    pub fn goto_last(&mut self) {
+        self.select_position(self.visible_len().saturating_sub(1));
+    }
+
+    /// Moves the middle panel viewport just enough to keep the selection
+    /// `SCROLLOFF` rows away from either edge.
+    fn update_scroll(&mut self) {
+        let height = self.list_height();
+        self.scroll = follow(self.scroll, self.position(), self.visible_len(), height);
+    }
+}
+
+/// Counts the bytes written to it, keeping none of them.
+struct ByteCounter(usize);
+
+impl io::Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Scrolls as little as possible from `scroll` to keep `position` in view
+/// with `SCROLLOFF` rows of context.
+pub(crate) fn follow(scroll: usize, position: usize, len: usize, height: usize) -> usize {
+    if height == 0 {
+        // Nothing is in view on a terminal too short for any rows.
+        return position;
     }
+    let margin = SCROLLOFF.min(height.saturating_sub(1) / 2);
+    let scroll = if position < scroll + margin {
+        position.saturating_sub(margin)
+    } else if position + margin >= scroll + height {
+        (position + margin + 1).saturating_sub(height)
+    } else {
+        scroll
+    };
+    scroll.min(len.saturating_sub(height))
+}
+
+/// Smallest scroll offset that shows `index` with `SCROLLOFF` rows of context.
+fn scroll_for(index: usize, len: usize, height: usize) -> usize {
+    let margin = SCROLLOFF.min(height.saturating_sub(1) / 2);
+    (index + margin + 1)
+        .saturating_sub(height)
+        .min(len.saturating_sub(height))
+}
+
+fn is_container(value: &Value) -> bool {
+    matches!(value, Value::Object(_) | Value::Array(_))
+}
+
+/// The segment leading from `parent` to its child named `key`.
+fn segment(parent: &Value, key: &str) -> PathSegment {
+    match (parent, key.parse()) {
+        (Value::Array(_), Ok(index)) => PathSegment::Index(index),
+        _ => PathSegment::Key(key.to_owned()),
+    }
+}
+
+fn child(value: &Value, index: usize) -> Option<&Value> {
+    match value {
+        Value::Object(map) => map.values().nth(index),
+        Value::Array(arr) => arr.get(index),
+        _ => None,
+    }
+}
+
+/// Indices of the children whose key matches `filter`.
+fn filtered_indices(children: &Children, filter: &Pattern) -> Vec<usize> {
+    (0..children.len())
+        .filter(|index| filter.is_match(&children.key(*index)))
+        .collect()
 }
 
 fn get_value_size(value: &Value) -> usize {
@@ -159,15 +1653,234 @@ fn get_value_size(value: &Value) -> usize {
 
 fn get_value_key(node: &Value, index: usize) -> String {
     match node {
-        Value::Object(map) => map
-            .keys()
-            .nth(index)
-            .expect("Index of of bounds")
-            .to_string(),
+        // An empty container has no key at any index; show nothing rather
+        // than panicking so the surrounding panels still render.
+        Value::Object(map) => map.keys().nth(index).cloned().unwrap_or_default(),
+        Value::Array(arr) if index >= arr.len() => String::new(),
         Value::Array(_) => index.to_string(),
-        Value::Bool(v) => v.to_string(),
         Value::String(v) => v.to_owned(),
-        Value::Number(v) => v.to_string(),
-        Value::Null => "null".to_owned(),
+        scalar => load::scalar_text(scalar),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    /// A terminal size roomy enough for three panels.
+    const SIZE: (u16, u16) = (120, 40);
+
+    fn fixture() -> Value {
+        json!({
+            "name": "jmv",
+            "tags": ["json", "viewer", "tui"],
+            "spec": {
+                "replicas": 3,
+                "containers": [
+                    {"name": "app", "ports": [80, 443]},
+                    {"name": "sidecar", "ports": []}
+                ]
+            },
+            "empty": null
+        })
+    }
+
+    #[test]
+    fn starts_on_the_first_key() {
+        let value = fixture();
+        let state = ProgramState::new(&value, SIZE);
+        assert_eq!(state.current_path(), ".name");
+        assert_eq!(state.current_subtree(), &json!("jmv"));
+        assert_eq!(state.depth(), 0);
+    }
+
+    #[test]
+    fn moves_within_a_level() {
+        let value = fixture();
+        let mut state = ProgramState::new(&value, SIZE);
+        state.inc_index(2);
+        assert_eq!(state.current_path(), ".spec");
+        state.inc_index(10);
+        assert_eq!(state.current_path(), ".empty");
+        assert_eq!(state.current_subtree(), &Value::Null);
+        state.dec_index(3);
+        assert_eq!(state.current_path(), ".name");
+        state.dec_index(1);
+        assert_eq!(state.current_path(), ".name");
+    }
+
+    #[test]
+    fn descends_and_ascends() {
+        let value = fixture();
+        let mut state = ProgramState::new(&value, SIZE);
+        state.inc_index(2);
+        state.push_path(1);
+        assert_eq!(state.current_path(), ".spec.replicas");
+        assert_eq!(state.current_subtree(), &json!(3));
+        state.inc_index(1);
+        state.push_path(2);
+        assert_eq!(state.current_path(), ".spec.containers[0].name");
+        assert_eq!(state.depth(), 3);
+        state.inc_index(1);
+        state.push_path(1);
+        assert_eq!(state.current_path(), ".spec.containers[0].ports[0]");
+        state.inc_index(1);
+        assert_eq!(state.current_subtree(), &json!(443));
+
+        state.pop_path(2);
+        assert_eq!(state.current_path(), ".spec.containers[0]");
+        state.inc_index(1);
+        state.push_path(1);
+        assert_eq!(state.current_path(), ".spec.containers[1].name");
+        state.pop_path(10);
+        assert_eq!(state.depth(), 0);
+        assert_eq!(state.current_path(), ".spec");
+    }
+
+    #[test]
+    fn remembers_the_selection_of_each_level() {
+        let value = fixture();
+        let mut state = ProgramState::new(&value, SIZE);
+        state.inc_index(1);
+        state.push_path(1);
+        state.inc_index(2);
+        assert_eq!(state.current_subtree(), &json!("tui"));
+        state.pop_path(1);
+        state.inc_index(1);
+        state.dec_index(1);
+        state.push_path(1);
+        assert_eq!(state.current_path(), ".tags[2]");
+    }
+
+    #[test]
+    fn entering_a_scalar_shows_it_alone() {
+        let value = fixture();
+        let mut state = ProgramState::new(&value, SIZE);
+        state.push_path(1);
+        assert_eq!(state.depth(), 1);
+        assert_eq!(state.current_path(), ".name");
+        assert_eq!(state.current_subtree(), &json!("jmv"));
+        state.inc_index(1);
+        state.pop_path(1);
+        assert_eq!(state.current_path(), ".name");
+    }
+
+    #[test]
+    fn walking_a_wide_object_looks_up_each_entry_directly() {
+        let value = Value::Object(
+            (0..10_000)
+                .map(|i| (format!("key{i:05}"), json!({ "i": i })))
+                .collect(),
+        );
+        let mut state = ProgramState::new(&value, SIZE);
+        // The entries are listed once, on entering the object.
+        let keys = state.entries().keys.as_ptr();
+        for index in 0..10_000 {
+            let panel = state.panel_state(PanelSide::Middle).unwrap();
+            let Some(Cow::Borrowed(children)) = panel.children else {
+                panic!("the middle panel listed the entries anew");
+            };
+            assert_eq!(children.keys.as_ptr(), keys);
+            assert_eq!(panel.text(), format!("key{index:05}"));
+            assert!(std::ptr::eq(
+                panel.selected.unwrap(),
+                children.values[index]
+            ));
+            state.inc_index(1);
+        }
+        assert_eq!(state.entries().keys.as_ptr(), keys);
+    }
+
+    #[test]
+    fn rows_past_u16_indices_stay_in_view() {
+        let value = Value::Array((0..70_000).map(|i| json!(i)).collect());
+        let mut state = ProgramState::new(&value, (80, 24));
+        state.goto_last();
+        assert_eq!(state.current_path(), "[69999]");
+        let middle = state.panel_state(PanelSide::Middle).unwrap();
+        assert_eq!(middle.index(), 69_999);
+        // The last of the 22 rows between the header and the status line.
+        assert_eq!(middle.row(), Some(21));
+        state.dec_index(69_999 - 65_536);
+        let middle = state.panel_state(PanelSide::Middle).unwrap();
+        assert_eq!(middle.index(), 65_536);
+        assert!(middle.row().is_some_and(|row| row < 22));
+    }
+
+    #[test]
+    fn folded_arrays_list_groups_of_entries() {
+        let value = Value::Array((0..70_000).map(|i| json!(i)).collect());
+        let mut state = ProgramState::new(&value, (80, 24));
+        state.set_folding(1000, 100);
+        state.goto_last();
+        assert_eq!(state.selected_type_label(), "group (100)");
+        state.push_path(1);
+        state.goto_last();
+        assert_eq!(state.current_path(), "[69999]");
+        assert_eq!(state.current_subtree(), &json!(69_999));
+    }
+
+    #[test]
+    fn a_scalar_root_has_nothing_to_navigate() {
+        let value = json!(true);
+        let mut state = ProgramState::new(&value, SIZE);
+        assert!(state.root_is_scalar());
+        state.inc_index(1);
+        state.push_path(1);
+        state.pop_path(1);
+        state.dec_index(1);
+        state.goto_last();
+        assert_eq!(state.depth(), 0);
+        assert_eq!(state.current_path(), ".");
+        assert_eq!(state.current_subtree(), &value);
+        assert_eq!(state.selected_type_label(), "boolean");
+        for side in state.panel_sides() {
+            state.panel_state(side);
+        }
+    }
+
+    #[test]
+    fn moving_in_empty_containers_stays_put() {
+        for value in [json!({}), json!([])] {
+            let mut state = ProgramState::new(&value, SIZE);
+            state.inc_index(1);
+            state.dec_index(1);
+            state.inc_index(usize::MAX);
+            state.goto_last();
+            assert_eq!(state.index(), 0);
+            assert_eq!(state.current_path(), ".");
+            assert_eq!(state.current_subtree(), &value);
+        }
+    }
+
+    #[test]
+    fn regexes_with_nested_repetition_fail_quickly() {
+        let value = json!({});
+        let mut state = ProgramState::new(&value, SIZE);
+        state.toggle_regex();
+        let pattern = state.pattern("(a*)*b").unwrap();
+        assert_eq!(pattern.find(&"a".repeat(10_000)), None);
+        assert_eq!(pattern.find("xaab"), Some(1..4));
+    }
+
+    #[test]
+    fn regexes_are_smart_case() {
+        let value = json!({});
+        let mut state = ProgramState::new(&value, SIZE);
+        state.toggle_regex();
+        assert_eq!(state.pattern(r"\dk").unwrap().find("1K"), Some(0..2));
+        assert_eq!(state.pattern(r"\dK").unwrap().find("1k"), None);
+    }
+
+    #[test]
+    fn bad_regexes_are_errors_only_as_regexes() {
+        let value = json!({});
+        let mut state = ProgramState::new(&value, SIZE);
+        assert!(state.query_error("(a").is_none());
+        state.toggle_regex();
+        assert!(state.query_error("(a").is_some());
+        assert!(state.pattern("(a").is_none());
     }
 }
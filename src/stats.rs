@@ -0,0 +1,74 @@
+use std::fmt::{self, Display};
+
+use serde_json::Value;
+
+use crate::path::{format_path, PathSegment};
+
+/// Counts describing the size and shape of a document.
+#[derive(Default)]
+pub struct DocStats {
+    /// Every value, containers and scalars alike, the root included.
+    pub nodes: usize,
+    /// Levels of nesting below the root, 0 for a scalar or empty root.
+    pub max_depth: usize,
+    pub objects: usize,
+    pub arrays: usize,
+    pub scalars: usize,
+    /// Path and length of the longest array, if there is any array.
+    pub largest_array: Option<(Vec<PathSegment>, usize)>,
+}
+
+impl DocStats {
+    /// Walks the whole of `root` once.
+    pub fn of(root: &Value) -> DocStats {
+        let mut stats = DocStats::default();
+        stats.visit(root, &mut Vec::new());
+        stats
+    }
+
+    fn visit(&mut self, value: &Value, path: &mut Vec<PathSegment>) {
+        self.nodes += 1;
+        self.max_depth = self.max_depth.max(path.len());
+        match value {
+            Value::Object(map) => {
+                self.objects += 1;
+                for (key, child) in map {
+                    path.push(PathSegment::Key(key.clone()));
+                    self.visit(child, path);
+                    path.pop();
+                }
+            }
+            Value::Array(arr) => {
+                self.arrays += 1;
+                if self
+                    .largest_array
+                    .as_ref()
+                    .is_none_or(|(_, len)| arr.len() > *len)
+                {
+                    self.largest_array = Some((path.clone(), arr.len()));
+                }
+                for (index, child) in arr.iter().enumerate() {
+                    path.push(PathSegment::Index(index));
+                    self.visit(child, path);
+                    path.pop();
+                }
+            }
+            _ => self.scalars += 1,
+        }
+    }
+}
+
+impl Display for DocStats {
+    /// A one-line summary, such as for the status bar.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} nodes, depth {}, {} objects, {} arrays, {} scalars",
+            self.nodes, self.max_depth, self.objects, self.arrays, self.scalars
+        )?;
+        if let Some((path, len)) = &self.largest_array {
+            write!(f, ", largest array {} ({len})", format_path(path))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,20 @@
+use serde_json::Value;
+
+/// The columns to lay out an array of objects as a table under: the keys
+/// every element has, in the order the first element lists them. `None`
+/// when `value` isn't a non-empty array of objects or they share no key.
+pub fn detect_table_schema(value: &Value) -> Option<Vec<String>> {
+    let Value::Array(elements) = value else {
+        return None;
+    };
+    let (first, rest) = elements.split_first()?;
+    let mut columns: Vec<String> = first.as_object()?.keys().cloned().collect();
+    for element in rest {
+        let object = element.as_object()?;
+        columns.retain(|key| object.contains_key(key));
+        if columns.is_empty() {
+            return None;
+        }
+    }
+    (!columns.is_empty()).then_some(columns)
+}
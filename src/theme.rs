@@ -0,0 +1,253 @@
+use std::{env, path::Path};
+
+use anyhow::{anyhow, bail, Context, Result};
+use crossterm::style::{Attribute, Color};
+use serde_json::Value;
+
+use cjv::load::{self, Format};
+
+/// Colors for each part of the screen.
+pub struct Theme {
+    pub selection_bg: Color,
+    pub selection_fg: Color,
+    /// Selection background while it sits on a search match.
+    pub match_bg: Color,
+    pub key: Color,
+    pub punctuation: Color,
+    pub string: Color,
+    pub number: Color,
+    pub bool: Color,
+    pub null: Color,
+    pub object: Color,
+    pub array: Color,
+    pub divider: Color,
+    /// The thumb of a panel scrollbar, whose track is drawn as a divider.
+    pub scrollbar: Color,
+    /// The `…` ending a key cut short to fit its panel.
+    pub truncated: Color,
+    /// The path shown on the top row.
+    pub header: Color,
+    pub status_bg: Color,
+    pub status_fg: Color,
+    /// Entries only in the second file of a `--diff`.
+    pub added: Color,
+    /// Entries only in the first file of a `--diff`.
+    pub removed: Color,
+    /// Entries in both files of a `--diff` that differ.
+    pub changed: Color,
+    /// Marks the selection and status bar with reverse video, for when
+    /// there are no colors to set them apart.
+    pub reverse: bool,
+    /// Marks the selection with reverse video whatever the colors.
+    pub reverse_selection: bool,
+    /// Puts a glyph ahead of `true`, `false` and `null`.
+    pub glyphs: bool,
+}
+
+impl Default for Theme {
+    fn default() -> Theme {
+        Theme {
+            selection_bg: Color::DarkBlue,
+            selection_fg: Color::Black,
+            match_bg: Color::DarkYellow,
+            key: Color::White,
+            punctuation: Color::Reset,
+            string: Color::Green,
+            number: Color::Cyan,
+            bool: Color::Yellow,
+            null: Color::DarkGrey,
+            object: Color::Blue,
+            array: Color::Magenta,
+            divider: Color::DarkGrey,
+            scrollbar: Color::Grey,
+            truncated: Color::DarkYellow,
+            header: Color::DarkGreen,
+            status_bg: Color::DarkGrey,
+            status_fg: Color::White,
+            added: Color::Green,
+            removed: Color::Red,
+            changed: Color::Yellow,
+            reverse: false,
+            reverse_selection: false,
+            glyphs: false,
+        }
+    }
+}
+
+impl Theme {
+    /// Leaves every color at the terminal default.
+    pub fn plain() -> Theme {
+        Theme {
+            selection_bg: Color::Reset,
+            selection_fg: Color::Reset,
+            match_bg: Color::Reset,
+            key: Color::Reset,
+            punctuation: Color::Reset,
+            string: Color::Reset,
+            number: Color::Reset,
+            bool: Color::Reset,
+            null: Color::Reset,
+            object: Color::Reset,
+            array: Color::Reset,
+            divider: Color::Reset,
+            scrollbar: Color::Reset,
+            truncated: Color::Reset,
+            header: Color::Reset,
+            status_bg: Color::Reset,
+            status_fg: Color::Reset,
+            added: Color::Reset,
+            removed: Color::Reset,
+            changed: Color::Reset,
+            reverse: true,
+            reverse_selection: false,
+            glyphs: false,
+        }
+    }
+
+    /// Reads a TOML or JSON file mapping roles such as `selection_bg` to
+    /// colors. Roles left out keep their default color.
+    pub fn load(path: &Path) -> Result<Theme> {
+        let text = std::fs::read_to_string(path).context("Theme Input")?;
+        let format = match Format::from_path(path) {
+            Format::Toml => Format::Toml,
+            _ => Format::Json,
+        };
+        let Value::Object(roles) = load::parse(&text, format).context("Theme Input")?.value else {
+            bail!("Theme Input: expected a table of roles");
+        };
+
+        let mut theme = Theme::default();
+        for (role, color) in roles {
+            let slot = theme
+                .role(&role)
+                .with_context(|| format!("Theme Input: unknown role `{role}`"))?;
+            let color = color
+                .as_str()
+                .and_then(parse_color)
+                .ok_or_else(|| anyhow!("Theme Input: invalid color {color} for `{role}`"))?;
+            *slot = color;
+        }
+        Ok(theme)
+    }
+
+    /// How a scalar reads: as JSON, after a ✓, ✗ or ∅ for `true`, `false`
+    /// or `null` with `glyphs` on so they stand out among the strings.
+    pub fn scalar_text(&self, value: &Value) -> String {
+        let glyph = match value {
+            Value::Bool(true) => '✓',
+            Value::Bool(false) => '✗',
+            Value::Null => '∅',
+            _ => return load::scalar_text(value),
+        };
+        if self.glyphs {
+            format!("{glyph} {}", load::scalar_text(value))
+        } else {
+            load::scalar_text(value)
+        }
+    }
+
+    /// The attribute for highlighted rows.
+    pub fn highlight(&self) -> Attribute {
+        if self.reverse || self.reverse_selection {
+            Attribute::Reverse
+        } else {
+            Attribute::NoReverse
+        }
+    }
+
+    /// The attribute for the status bar.
+    pub fn status_highlight(&self) -> Attribute {
+        if self.reverse {
+            Attribute::Reverse
+        } else {
+            Attribute::NoReverse
+        }
+    }
+
+    pub fn value_color(&self, value: &Value) -> Color {
+        match value {
+            Value::String(_) => self.string,
+            Value::Number(_) => self.number,
+            Value::Bool(_) => self.bool,
+            Value::Null => self.null,
+            Value::Object(_) => self.object,
+            Value::Array(_) => self.array,
+        }
+    }
+
+    fn role(&mut self, name: &str) -> Option<&mut Color> {
+        Some(match name {
+            "selection_bg" => &mut self.selection_bg,
+            "selection_fg" => &mut self.selection_fg,
+            "match_bg" => &mut self.match_bg,
+            "key" => &mut self.key,
+            "punctuation" => &mut self.punctuation,
+            "string" => &mut self.string,
+            "number" => &mut self.number,
+            "bool" => &mut self.bool,
+            "null" => &mut self.null,
+            "object" => &mut self.object,
+            "array" => &mut self.array,
+            "divider" => &mut self.divider,
+            "scrollbar" => &mut self.scrollbar,
+            "truncated" => &mut self.truncated,
+            "header" => &mut self.header,
+            "status_bg" => &mut self.status_bg,
+            "status_fg" => &mut self.status_fg,
+            "added" => &mut self.added,
+            "removed" => &mut self.removed,
+            "changed" => &mut self.changed,
+            _ => return None,
+        })
+    }
+}
+
+/// Whether to draw in color on a screen that is a `terminal` or not: not
+/// when `NO_COLOR` is set to anything, as <https://no-color.org> asks, nor
+/// when the screen isn't a terminal.
+pub fn color_enabled(terminal: bool) -> bool {
+    let no_color = env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+    !no_color && terminal
+}
+
+/// Parses `#rrggbb`, or a color name such as `dark_blue` in any case with
+/// optional `_`, `-` or spaces between words.
+pub fn parse_color(text: &str) -> Option<Color> {
+    if let Some(hex) = text.strip_prefix('#') {
+        if hex.len() != 6 || !hex.is_ascii() {
+            return None;
+        }
+        let channel = |at: usize| u8::from_str_radix(&hex[at..at + 2], 16).ok();
+        return Some(Color::Rgb {
+            r: channel(0)?,
+            g: channel(2)?,
+            b: channel(4)?,
+        });
+    }
+
+    let name: String = text
+        .chars()
+        .filter(|c| !matches!(c, '_' | '-' | ' '))
+        .collect::<String>()
+        .to_lowercase();
+    Some(match name.as_str() {
+        "reset" | "default" => Color::Reset,
+        "black" => Color::Black,
+        "darkgrey" | "darkgray" => Color::DarkGrey,
+        "red" => Color::Red,
+        "darkred" => Color::DarkRed,
+        "green" => Color::Green,
+        "darkgreen" => Color::DarkGreen,
+        "yellow" => Color::Yellow,
+        "darkyellow" => Color::DarkYellow,
+        "blue" => Color::Blue,
+        "darkblue" => Color::DarkBlue,
+        "magenta" => Color::Magenta,
+        "darkmagenta" => Color::DarkMagenta,
+        "cyan" => Color::Cyan,
+        "darkcyan" => Color::DarkCyan,
+        "white" => Color::White,
+        "grey" | "gray" => Color::Grey,
+        _ => return None,
+    })
+}
@@ -0,0 +1,213 @@
+use std::collections::HashSet;
+
+use serde_json::Value;
+
+use crate::{path::PathSegment, state::follow};
+
+/// One line of the tree view.
+pub struct TreeRow<'a> {
+    /// Nesting level, 0 for children of the root.
+    pub depth: usize,
+    pub path: Vec<PathSegment>,
+    /// The object key or array index leading to the value.
+    pub key: String,
+    pub value: &'a Value,
+    pub expanded: bool,
+}
+
+/// A single-pane view of the document as an indented tree, where each
+/// container can be expanded in place.
+pub struct TreeState<'a> {
+    root: &'a Value,
+    expanded: HashSet<Vec<PathSegment>>,
+    rows: Vec<TreeRow<'a>>,
+    index: usize,
+    scroll: usize,
+    height: usize,
+}
+
+impl<'a> TreeState<'a> {
+    /// Opens the tree with every container along `path` expanded and the
+    /// row for `path` selected, showing `height` rows at a time.
+    pub fn new(root: &'a Value, path: &[PathSegment], height: usize) -> TreeState<'a> {
+        let mut tree = TreeState {
+            root,
+            expanded: HashSet::new(),
+            rows: Vec::new(),
+            index: 0,
+            scroll: 0,
+            height,
+        };
+        tree.reveal(path);
+        tree
+    }
+
+    pub fn rows(&self) -> &[TreeRow<'a>] {
+        &self.rows
+    }
+
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn scroll(&self) -> usize {
+        self.scroll
+    }
+
+    /// Path of the selected row, or the root when the tree is empty.
+    pub fn selected_path(&self) -> &[PathSegment] {
+        self.rows.get(self.index).map_or(&[], |row| &row.path)
+    }
+
+    /// Expands the containers leading to `path` and selects its row, or the
+    /// deepest row on the way there that exists.
+    pub fn reveal(&mut self, path: &[PathSegment]) {
+        for depth in 1..path.len() {
+            self.expanded.insert(path[..depth].to_vec());
+        }
+        self.rebuild();
+        let found = (1..=path.len())
+            .rev()
+            .find_map(|len| self.rows.iter().position(|row| row.path == path[..len]));
+        self.select(found.unwrap_or(0));
+    }
+
+    pub fn resize(&mut self, height: usize) {
+        self.height = height;
+        self.select(self.index);
+    }
+
+    /// Selects the row shown `row` lines below the top of the view.
+    pub fn select_row(&mut self, row: usize) {
+        if row < self.height && self.scroll + row < self.rows.len() {
+            self.select(self.scroll + row);
+        }
+    }
+
+    pub fn inc_index(&mut self, count: usize) {
+        self.select(self.index.saturating_add(count));
+    }
+
+    pub fn dec_index(&mut self, count: usize) {
+        self.select(self.index.saturating_sub(count));
+    }
+
+    pub fn goto_first(&mut self) {
+        self.select(0);
+    }
+
+    pub fn goto_last(&mut self) {
+        self.select(self.rows.len().saturating_sub(1));
+    }
+
+    /// Expands the selected container, or steps into its first child if it
+    /// is already expanded.
+    pub fn expand(&mut self) {
+        let Some(row) = self.rows.get(self.index) else {
+            return;
+        };
+        if !has_children(row.value) {
+            return;
+        }
+        if row.expanded {
+            self.select(self.index + 1);
+        } else {
+            self.set_expanded(true);
+        }
+    }
+
+    /// Collapses the selected container, or steps out to its parent if it
+    /// is already collapsed.
+    pub fn collapse(&mut self) {
+        let Some(row) = self.rows.get(self.index) else {
+            return;
+        };
+        if row.expanded {
+            self.set_expanded(false);
+            return;
+        }
+        let depth = row.depth;
+        if let Some(parent) = self.rows[..self.index]
+            .iter()
+            .rposition(|row| row.depth < depth)
+        {
+            self.select(parent);
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        match self.rows.get(self.index) {
+            Some(row) if row.expanded => self.set_expanded(false),
+            Some(row) if has_children(row.value) => self.set_expanded(true),
+            _ => {}
+        }
+    }
+
+    fn set_expanded(&mut self, expanded: bool) {
+        let path = self.rows[self.index].path.clone();
+        if expanded {
+            self.expanded.insert(path);
+        } else {
+            self.expanded.remove(&path);
+        }
+        self.rebuild();
+        self.select(self.index);
+    }
+
+    fn select(&mut self, index: usize) {
+        self.index = index.min(self.rows.len().saturating_sub(1));
+        self.scroll = follow(self.scroll, self.index, self.rows.len(), self.height);
+    }
+
+    fn rebuild(&mut self) {
+        let mut rows = Vec::new();
+        flatten(self.root, &self.expanded, 0, &mut Vec::new(), &mut rows);
+        self.rows = rows;
+    }
+}
+
+fn has_children(value: &Value) -> bool {
+    match value {
+        Value::Object(map) => !map.is_empty(),
+        Value::Array(arr) => !arr.is_empty(),
+        _ => false,
+    }
+}
+
+/// Appends a row for each child of `value`, followed by the rows of its own
+/// children if it is expanded.
+fn flatten<'a>(
+    value: &'a Value,
+    expanded: &HashSet<Vec<PathSegment>>,
+    depth: usize,
+    path: &mut Vec<PathSegment>,
+    rows: &mut Vec<TreeRow<'a>>,
+) {
+    let children: Box<dyn Iterator<Item = (PathSegment, String, &Value)>> = match value {
+        Value::Object(map) => Box::new(
+            map.iter()
+                .map(|(k, v)| (PathSegment::Key(k.clone()), k.clone(), v)),
+        ),
+        Value::Array(arr) => Box::new(
+            arr.iter()
+                .enumerate()
+                .map(|(i, v)| (PathSegment::Index(i), i.to_string(), v)),
+        ),
+        _ => return,
+    };
+    for (step, key, child) in children {
+        path.push(step);
+        let is_expanded = has_children(child) && expanded.contains(path);
+        rows.push(TreeRow {
+            depth,
+            path: path.clone(),
+            key,
+            value: child,
+            expanded: is_expanded,
+        });
+        if is_expanded {
+            flatten(child, expanded, depth + 1, path, rows);
+        }
+        path.pop();
+    }
+}
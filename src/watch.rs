@@ -0,0 +1,62 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver},
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+/// How long the file must stay quiet after a change before it is reloaded,
+/// so editors that write in several steps don't trigger a parse mid-write.
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+pub struct FileWatcher {
+    _watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<notify::Event>>,
+    file: PathBuf,
+    changed_at: Option<Instant>,
+}
+
+impl FileWatcher {
+    /// Watches the directory containing `path` rather than the file itself,
+    /// since editors often save by replacing the file with a new one.
+    pub fn new(path: &Path) -> Result<FileWatcher> {
+        let file = path.canonicalize().context("Watch Path")?;
+        let dir = file.parent().context("Watch Path")?;
+
+        let (tx, events) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(tx).context("File Watcher")?;
+        watcher
+            .watch(dir, RecursiveMode::NonRecursive)
+            .context("File Watcher")?;
+
+        Ok(FileWatcher {
+            _watcher: watcher,
+            events,
+            file,
+            changed_at: None,
+        })
+    }
+
+    /// Whether the file has changed and then settled since the last call.
+    pub fn changed(&mut self) -> bool {
+        while let Ok(event) = self.events.try_recv() {
+            let Ok(event) = event else {
+                continue;
+            };
+            let touched = matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_))
+                && event.paths.contains(&self.file);
+            if touched {
+                self.changed_at = Some(Instant::now());
+            }
+        }
+
+        if self.changed_at.is_some_and(|at| at.elapsed() >= DEBOUNCE) {
+            self.changed_at = None;
+            return true;
+        }
+        false
+    }
+}